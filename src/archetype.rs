@@ -0,0 +1,113 @@
+use std::collections::BTreeMap;
+
+use fnv::FnvHashMap;
+
+use crate::context::Context;
+use crate::deck::{Deck, DeckBTree};
+
+// The least confidence for an archetype to be named.
+pub const MIN_CONFIDENCE: f64 = 0.25;
+// The most archetypes a deck is named after.
+pub const MAX_ARCHETYPES: usize = 2;
+
+// The fraction of the cards of the cardpool that carry each tag.
+fn baseline(ctx: &Context) -> FnvHashMap<&str, f64> {
+    let mut ret = FnvHashMap::default();
+    let n = ctx.cards_vec.0.len();
+    for idx in 0..n {
+        for tag in &ctx.idx_to_card(idx).tags_ {
+            *ret.entry(tag.as_str()).or_insert(0.0) += 1.0 / n as f64;
+        }
+    }
+    ret
+}
+
+// The fraction of the copies of the deck that carry each tag.
+pub(crate) fn tag_densities<'a>(ctx: &'a Context, deck: &DeckBTree) -> FnvHashMap<&'a str, f64> {
+    let len = deck.len() as f64;
+    let mut ret = FnvHashMap::default();
+    for (idx, qty) in &deck.0 {
+        for tag in &ctx.idx_to_card(*idx).tags_ {
+            *ret.entry(tag.as_str()).or_insert(0.0) += *qty as f64 / len;
+        }
+    }
+    ret
+}
+
+// The archetypes of the deck, with their confidence, most likely first.
+// Some tags (Evolve, Buff...) are common to most cards, so a tag is only evidence of an archetype
+// by how much denser it is in the deck than in the cardpool. The confidences are these excess
+// densities, normalized to sum up to 1.
+pub fn classify(ctx: &Context, deck: &DeckBTree) -> Vec<(String, f64)> {
+    let baseline = baseline(ctx);
+    let mut ret: Vec<(String, f64)> = tag_densities(ctx, deck).into_iter()
+        .map(|(tag, density)| (tag.to_string(), density - baseline.get(tag).unwrap_or(&0.0)))
+        .filter(|(_, excess)| *excess > 0.0)
+        .collect();
+    let total: f64 = ret.iter().map(|(_, excess)| excess).sum();
+    ret.iter_mut().for_each(|(_, excess)| *excess /= total);
+    ret.sort_by(|(a, x), (b, y)| y.partial_cmp(x).unwrap().then(a.cmp(b)));
+    ret
+}
+
+// A name for the deck from its dominant archetypes, like "Fairy Forest" or "Levin / Rally Sword".
+pub fn label(ctx: &Context, deck: &DeckBTree) -> String {
+    let craft = ctx.craft_name().trim_end_matches("craft");
+    let archetypes: Vec<String> = classify(ctx, deck).into_iter()
+        .filter(|(_, confidence)| *confidence >= MIN_CONFIDENCE)
+        .take(MAX_ARCHETYPES)
+        .map(|(tag, _)| tag)
+        .collect();
+    if archetypes.is_empty() {
+        format!("Generic {}", craft)
+    } else {
+        format!("{} {}", archetypes.join(" / "), craft)
+    }
+}
+
+// The label with the confidence of each archetype.
+pub fn label_as_string(ctx: &Context, deck: &DeckBTree) -> String {
+    let confidences: Vec<String> = classify(ctx, deck).into_iter()
+        .take(MAX_ARCHETYPES + 1)
+        .map(|(tag, confidence)| format!("{} {:.0}%", tag, 100.0 * confidence))
+        .collect();
+    format!("{} ({})", label(ctx, deck), confidences.join(", "))
+}
+
+// The decks grouped by label: each label maps to the indexes of its decks.
+pub fn clusters(ctx: &Context, decks: &[DeckBTree]) -> BTreeMap<String, Vec<usize>> {
+    let mut ret: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (i, deck) in decks.iter().enumerate() {
+        ret.entry(label(ctx, deck)).or_default().push(i);
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::archetype::{classify, clusters, label};
+    use crate::context::Context;
+    use crate::deck::{Deck, DeckBTree, deck_of_cards};
+
+    // A deck made of the cards that carry only the given tag.
+    fn deck_of(ctx: &Context, tag: &str) -> DeckBTree {
+        let mut ret = deck_of_cards(ctx, |card| card.tags_ == vec![tag.to_string()]);
+        ret.rand_fill(ctx);
+        ret
+    }
+
+    #[test]
+    fn archetypes() {
+        let ctx = Context::from_debug();
+        let rally = deck_of(&ctx, "Rally");
+        let archetypes = classify(&ctx, &rally);
+        assert_eq!(archetypes[0].0, "Rally");
+        let total: f64 = archetypes.iter().map(|(_, c)| c).sum();
+        assert!(f64::abs(total - 1.0) < 1e-9);
+        assert!(label(&ctx, &rally).starts_with("Rally"));
+        assert!(label(&ctx, &rally).ends_with(" Sword"));
+        let decks = vec![rally.clone(), deck_of(&ctx, "Levin"), rally];
+        let clusters = clusters(&ctx, &decks);
+        assert_eq!(clusters[&label(&ctx, &decks[0])], vec![0, 2]);
+    }
+}
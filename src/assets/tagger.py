@@ -1,4 +1,6 @@
 import json
+import re
+import sys
 
 
 def load_cardpool(rot: bool):
@@ -121,10 +123,57 @@ def autotag_card(card):
     return tags
 
 
+# What the card does in a game, regardless of the archetype.
+ROLES = {
+    "Removal": r"(destroy|banish|deal (\d+|x) damage to|return) (an|a|\d+) (enemy|random enemy|other)? ?follower"
+               r"|(destroy|banish|deal (\d+|x) damage to) an enemy",
+    "AoE": r"(all|each) (enemy|other)? ?followers|(all|each) followers",
+    "Draw": r"\bdraw\b",
+    "Heal": r"\brestore\b",
+    "Ward": r"\bward\b",
+    "Finisher": r"\bstorm\b|deal ([3-9]|\d{2}|x) damage to the enemy leader",
+    "Ramp": r"empty play point|gain \d+ play point",
+}
+
+
+def autorole_card(card):
+    return {role for role, pattern in ROLES.items() if re.search(pattern, card["effect_"])}
+
+
+# The other costs the card can be played at. Choose cards are played at their own cost.
+ALT_COSTS = {
+    "Accelerate": r"accelerate \((\d+)\)",
+    "Crystallize": r"crystallize \((\d+)\)",
+    "Enhance": r"enhance \((\d+)\)",
+}
+# Cards that make themselves cheaper, by an amount that depends on the game.
+SELF_REDUCTION = r"subtract (\d+|x) from (the cost of this card|this card's cost)"
+
+
+def alt_costs(card):
+    ret = set()
+    for kind, pattern in ALT_COSTS.items():
+        for pp in re.findall(pattern, card["effect_"]):
+            if int(pp) != card["pp_"]:
+                ret.add((kind, int(pp)))
+    if re.search(SELF_REDUCTION, card["effect_"]) and card["pp_"] > 1:
+        # a rough estimate: the card is usually played at about half its cost.
+        ret.add(("Reduce", card["pp_"] // 2))
+    return sorted(ret)
+
+
 if __name__ == "__main__":
-    pool = load_cardpool(False)
+    # "roles": only the roles and alternate costs of the existing cards.json are computed again.
+    if sys.argv[1:] == ["roles"]:
+        with open('cards.json', 'r') as f:
+            pool = json.load(f)
+    else:
+        pool = load_cardpool(False)
+        for i, card in pool.items():
+            card["tags_"] = list(autotag_card(card))
     for i, card in pool.items():
-        card["tags_"] = list(autotag_card(card))
+        card["roles_"] = sorted(autorole_card(card))
+        card["alt_costs_"] = [list(c) for c in alt_costs(card)]
         pool[i] = card
     with open('cards.json', 'w+') as f:
         json.dump(pool, f, ensure_ascii=False)
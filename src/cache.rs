@@ -0,0 +1,144 @@
+use std::collections::BTreeMap;
+
+use fnv::FnvHashMap;
+
+use crate::context::Context;
+use crate::deck::Deck;
+use crate::fitness::Fitness;
+
+// Scores of the decks rated recently, keyed by deck hash. Elitism and crossover produce a lot of
+// duplicates, which are then rated only once. When the cache is full the least recently used
+// score is evicted. The decks are stored along with their scores, so that two decks with the same
+// hash are never mistaken for each other.
+// NOTE: the scores are only valid for the context and fitness they were computed with, so the
+// cache has to be cleared when those change.
+pub struct FitnessCache<D> {
+    // deck hash -> (deck, score, time of last use)
+    scores: FnvHashMap<u64, (D, f64, u64)>,
+    // time of last use -> deck hash, to find the least recently used entry.
+    uses: BTreeMap<u64, u64>,
+    clock: u64,
+    pub capacity: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl<D: Deck + Clone + PartialEq> FitnessCache<D> {
+    // A capacity of 0 disables the cache.
+    pub fn new(capacity: usize) -> Self {
+        FitnessCache {
+            scores: FnvHashMap::default(),
+            uses: BTreeMap::new(),
+            clock: 0,
+            capacity,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.scores.clear();
+        self.uses.clear();
+    }
+
+    pub fn get(&mut self, deck: &D) -> Option<f64> {
+        self.clock += 1;
+        let key = deck.key();
+        match self.scores.get_mut(&key) {
+            Some((cached, score, last_use)) if cached == deck => {
+                self.uses.remove(last_use);
+                self.uses.insert(self.clock, key);
+                *last_use = self.clock;
+                self.hits += 1;
+                Some(*score)
+            }
+            _ => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    // A deck with the same hash as a cached one takes its place.
+    pub fn insert(&mut self, deck: &D, score: f64) {
+        if self.capacity == 0 { return; }
+        self.clock += 1;
+        let key = deck.key();
+        if let Some((_, _, last_use)) = self.scores.insert(key, (deck.clone(), score, self.clock)) {
+            self.uses.remove(&last_use);
+        }
+        self.uses.insert(self.clock, key);
+        while self.scores.len() > self.capacity {
+            let (_, lru) = self.uses.pop_first().unwrap();
+            self.scores.remove(&lru);
+        }
+    }
+
+    // Rates the deck, unless its score is already known.
+    pub fn rate(&mut self, deck: &D, ctx: &Context, fitness: &Fitness<D>) -> f64 {
+        if let Some(score) = self.get(deck) { return score; }
+        let score = deck.rate(ctx, fitness);
+        self.insert(deck, score);
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cache::FitnessCache;
+    use crate::context::Context;
+    use crate::deck::{Deck, DeckBTree};
+    use crate::fitness::Fitness;
+
+    // A deck with a single copy of the card.
+    fn deck(idx: usize) -> DeckBTree {
+        let mut ret = DeckBTree::new();
+        ret.add(idx);
+        ret
+    }
+
+    #[test]
+    fn lru() {
+        let mut c = FitnessCache::new(2);
+        c.insert(&deck(1), 0.1);
+        c.insert(&deck(2), 0.2);
+        // 1 becomes the most recently used entry, so 2 is evicted.
+        assert_eq!(c.get(&deck(1)), Some(0.1));
+        c.insert(&deck(3), 0.3);
+        assert_eq!(c.scores.len(), 2);
+        assert_eq!(c.get(&deck(2)), None);
+        assert_eq!(c.get(&deck(3)), Some(0.3));
+        assert_eq!((c.hits, c.misses), (2, 1));
+    }
+
+    #[test]
+    fn disabled() {
+        let mut c = FitnessCache::new(0);
+        c.insert(&deck(1), 0.1);
+        assert_eq!(c.get(&deck(1)), None);
+        assert!(c.scores.is_empty());
+    }
+
+    #[test]
+    fn collision() {
+        let mut c = FitnessCache::new(2);
+        c.insert(&deck(1), 0.1);
+        // another deck stored under the same hash isn't returned for it.
+        let key = deck(1).key();
+        let (_, score, last_use) = c.scores.remove(&key).unwrap();
+        c.scores.insert(key, (deck(2), score, last_use));
+        assert_eq!(c.get(&deck(1)), None);
+    }
+
+    #[test]
+    fn rate() {
+        let ctx = Context::from_debug();
+        let f = Fitness::from_weights(0.4, 0.4, 0.2);
+        let mut c = FitnessCache::new(10);
+        let d = DeckBTree::from_rand(&ctx);
+        let score = c.rate(&d, &ctx, &f);
+        assert_eq!(c.rate(&d.clone(), &ctx, &f), score);
+        assert_eq!(score, d.rate(&ctx, &f));
+        assert_eq!((c.hits, c.misses), (1, 1));
+    }
+}
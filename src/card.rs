@@ -1,6 +1,6 @@
 use serde::Deserialize;
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 pub struct CardInfo {
     pub craft_: String,
     pub id_: i32,
@@ -10,4 +10,13 @@ pub struct CardInfo {
     pub rotation_: bool,
     pub name_: String,
     pub tags_: Vec<String>,
+    // The card's effect text.
+    #[serde(default)]
+    pub effect_: String,
+    // What the card does in a game (removal, draw...), see tagger.py.
+    #[serde(default)]
+    pub roles_: Vec<String>,
+    // The other costs the card can be played at (accelerate, enhance...), see tagger.py.
+    #[serde(default)]
+    pub alt_costs_: Vec<(String, u8)>,
 }
\ No newline at end of file
@@ -4,6 +4,7 @@ use std::io;
 use fnv::FnvHashMap;
 
 use crate::card::CardInfo;
+use crate::locus::{Attributes, LocusOrdering};
 
 fn clear_term() {
     print!("{}[2J", 27 as char);
@@ -46,6 +47,7 @@ const CRAFTS: [&str; 8] = [
 // Card ID to card info mapping.
 // Fnv is preferred to std::collections::HashMap because the speed-for-safety tradeoff that the
 // latter makes isn't needed.
+#[derive(Clone)]
 pub struct CardsMap(pub FnvHashMap<i32, CardInfo>);
 
 impl CardsMap {
@@ -63,6 +65,7 @@ impl CardsMap {
 }
 
 // Vector of card IDs.
+#[derive(Clone)]
 pub struct CardsVec(pub Vec<i32>);
 
 impl CardsVec {
@@ -75,18 +78,15 @@ impl CardsVec {
 
     // This sorting phase encourages the creation of low-order schemata (in this case,
     // synergistic card packages) by clumping "similar" cards in the same genetic locus.
+    // See locus.rs for the other orderings.
 
     fn sort(&mut self, data: &CardsMap) {
-        self.0.sort_unstable_by_key(|c| data.0.get(c).unwrap().id_);
-        self.0.sort_by_key(|c| data.0.get(c).unwrap().trait_.to_string());
-        self.0.sort_by_key(|c| data.0.get(c).unwrap().craft_.to_string());
-        self.0.sort_by_key(|c| data.0.get(c).unwrap().type_.to_string());
-        self.0.sort_by_key(|c| &data.0.get(c).unwrap().tags_);
-        self.0.sort_by_key(|c| data.0.get(c).unwrap().pp_);
+        Attributes.order(data, &mut self.0);
     }
 }
 
 // A bundle of variables set by the user at runtime.
+#[derive(Clone)]
 pub struct Context {
     pub cards_map: CardsMap,
     pub cards_vec: CardsVec,
@@ -159,6 +159,25 @@ impl Context {
     pub fn idx_to_card(&self, idx: usize) -> &CardInfo {
         self.cards_map.0.get(&self.cards_vec.0[idx]).unwrap()
     }
+
+    pub fn craft_name(&self) -> &str {
+        CRAFTS[self.craft]
+    }
+
+    pub fn game_mode_name(&self) -> &str {
+        if self.game_mode == 0 { "Rotation" } else { "Unlimited" }
+    }
+
+    // Puts the cards in the order given.
+    // NOTE: decks refer to cards by their locus, so the decks made before reordering become invalid.
+    pub fn reorder(&mut self, ordering: &dyn LocusOrdering) {
+        ordering.order(&self.cards_map, &mut self.cards_vec.0);
+    }
+
+    // NOTE: returns None if the card isn't in the cardpool of the chosen craft and format.
+    pub fn id_to_idx(&self, id: i32) -> Option<usize> {
+        self.cards_vec.0.iter().position(|c| *c == id)
+    }
 }
 
 #[cfg(test)]
@@ -1,15 +1,22 @@
 use std::cmp::{max, min};
 use std::collections::btree_map::BTreeMap;
+use std::hash::{Hash, Hasher};
+
+use fnv::FnvHasher;
 
 use rand::Rng;
 
+#[cfg(test)]
+use crate::card::CardInfo;
 use crate::context::Context;
+use crate::distance;
+use crate::fitness::Fitness;
 
 fn invert(s: String) -> String {
     s.chars().rev().collect()
 }
 
-fn hist(l: &[i32; PP_CURVE_SIZE], symbol: &str, gap: usize) -> String {
+pub(crate) fn hist(l: &[i32; PP_CURVE_SIZE], symbol: &str, gap: usize) -> String {
     fn blanks(n: usize) -> String {
         " ".repeat(n)
     }
@@ -35,9 +42,14 @@ fn hist(l: &[i32; PP_CURVE_SIZE], symbol: &str, gap: usize) -> String {
     result + "+\n"
 }
 
-const PP_CURVE_SIZE: usize = 8;
-const DECK_SIZE: i8 = 40;
-const MAX_QTY: i8 = 3;
+pub(crate) const PP_CURVE_SIZE: usize = 8;
+
+// The index of the pp curve bucket of a cost.
+pub(crate) fn pp_bucket(pp: u8) -> usize {
+    (pp as usize).clamp(1, PP_CURVE_SIZE) - 1
+}
+pub(crate) const DECK_SIZE: i8 = 40;
+pub(crate) const MAX_QTY: i8 = 3;
 
 // The behavior is separated from the underlying data structure in the interest of testing
 // alternative deck encodings. Currently this doesn't result in a practical advantage, because
@@ -45,30 +57,37 @@ const MAX_QTY: i8 = 3;
 // This separation is done nonetheless because there is still some small gain in modularity,
 // and in the event that Rust introduces such a trait the refactoring would be straightforward.
 
-pub trait Deck {
+pub trait Deck: Sized {
     type Card;
     fn new() -> Self;
     // basic methods for deck manipulation and monitoring
     fn len(&self) -> i8;
+    fn key(&self) -> u64;
     fn add(&mut self, c: Self::Card);
     fn rand_idx(&self) -> usize;
     fn rand_fill(&mut self, ctx: &Context);
     fn from_rand(ctx: &Context) -> Self;
     fn cut(&mut self, c: Self::Card);
+    fn repair(&mut self, ctx: &Context);
     fn pp_curve(&self, ctx: &Context) -> [i32; PP_CURVE_SIZE];
+    fn effective_pp_curve(&self, ctx: &Context) -> [f64; PP_CURVE_SIZE];
     // methods for displaying
     fn as_string(&self, ctx: &Context) -> String;
     fn url(&self, ctx: &Context) -> String;
     // methods for the GA
-    fn rate(&self, ctx: &Context, w_curve: f64, w_length: f64, w_tags: f64) -> f64;
+    fn rate(&self, ctx: &Context, fitness: &Fitness<Self>) -> f64;
+    fn objectives(&self, ctx: &Context, fitness: &Fitness<Self>) -> Vec<f64>;
     fn mutate(&mut self, ctx: &Context, temp: isize, temp_min: isize, temp_annealing: f64, time: f64);
     fn mix(&self, other: &Self) -> Self;
+    fn distance(&self, other: &Self) -> i8;
+    fn tag_distance(&self, ctx: &Context, other: &Self) -> f64;
+    fn approach(&mut self, other: &Self);
 }
 
 // In the chosen deck encoding the keys are cards, represented by their indexes relative to
 // CardList, and map to their number of copies.
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct DeckBTree(pub BTreeMap<usize, i8>);
 
 
@@ -84,6 +103,17 @@ impl Deck for DeckBTree {
         self.0.iter().fold(0, |s, el| s + el.1)
     }
 
+    // A hash of the deck contents: two decks with the same cards and copies share it, regardless
+    // of how they were built.
+    fn key(&self) -> u64 {
+        let mut hasher = FnvHasher::default();
+        for (idx, qty) in &self.0 {
+            idx.hash(&mut hasher);
+            qty.hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
     // NOTE: silently fails if the card to add is already at its maximum amount of copies.
     // NOTE: assumes that the deck isn't full.
     fn add(&mut self, card: usize) {
@@ -130,16 +160,46 @@ impl Deck for DeckBTree {
         }
     }
 
+    // Turns a deck of any size into a full one, by cutting random copies or adding random cards.
+    fn repair(&mut self, ctx: &Context) {
+        while self.len() > DECK_SIZE {
+            let roll = rand::thread_rng().gen_range(1, self.len() + 1);
+            let mut chk = 0;
+            let mut cut = 0;
+            for (idx, qty) in &self.0 {
+                chk += *qty;
+                if roll <= chk {
+                    cut = *idx;
+                    break;
+                }
+            }
+            self.cut(cut);
+        }
+        self.rand_fill(ctx);
+    }
+
     // Returns a list where the ith element is the number of cards in the deck costing i+1 pps.
     // The first and last bucket also contain all the cards costing, respectively, less than or more
     // than the pp cost of the bucket.
     fn pp_curve(&self, ctx: &Context) -> [i32; PP_CURVE_SIZE] {
         let mut ret = [0; PP_CURVE_SIZE];
         for (idx, qty) in &self.0 {
-            let mut pp_idx = ctx.idx_to_card(*idx).pp_ as usize;
-            pp_idx = min(pp_idx, PP_CURVE_SIZE);
-            pp_idx = max(pp_idx, 1);
-            ret[pp_idx - 1] += *qty as i32;
+            ret[pp_bucket(ctx.idx_to_card(*idx).pp_)] += *qty as i32;
+        }
+        ret
+    }
+
+    // Like pp_curve, but cards that can be played at other costs are split evenly between the
+    // buckets of their cost and of each alternative.
+    fn effective_pp_curve(&self, ctx: &Context) -> [f64; PP_CURVE_SIZE] {
+        let mut ret = [0.0; PP_CURVE_SIZE];
+        for (idx, qty) in &self.0 {
+            let card = ctx.idx_to_card(*idx);
+            let share = *qty as f64 / (card.alt_costs_.len() + 1) as f64;
+            ret[pp_bucket(card.pp_)] += share;
+            for (_, pp) in &card.alt_costs_ {
+                ret[pp_bucket(*pp)] += share;
+            }
         }
         ret
     }
@@ -183,47 +243,14 @@ impl Deck for DeckBTree {
     }
 
     // The fitness score is a float between 0 and 1, the higher the better the deck is.
-    // Its value is a weighed sum of sub-scores.
-    fn rate(&self, ctx: &Context, w_curve: f64, w_length: f64, w_tags: f64) -> f64 {
-        // FIRST SCORE: how similar the deck curve is to an arbitrary one.
-        // The arbitrary curve that the deck should approach.
-        let curve_ideal = [4, 14, 6, 5, 4, 3, 2, 2];
-        // The euclidean norm of the above.
-        let curve_ideal_module = 17.4928556845359;
-        // The minimum cosine similarity between the pp curve above and the possible pp curves of a
-        // 40 cards deck.
-        let curve_min_score = 0.1143323900950059;
-        let curve_max_score = 1.0 - curve_min_score;
-        let curve_self = self.pp_curve(&ctx);
-        let mut curve_score = 0.0;
-        let mut module_self = 0.0;
-        // Calculating the cosine similarity.
-        for i in 0..PP_CURVE_SIZE {
-            curve_score += (curve_ideal[i] * curve_self[i]).abs() as f64;
-            module_self += curve_self[i].pow(2) as f64;
-        }
-        curve_score /= curve_ideal_module * module_self.sqrt();
-        // Normalizing the score between 0 and 1.
-        curve_score = (curve_score - curve_min_score) / curve_max_score;
-        // SECOND SCORE: the number of cards with different names in the deck, taken as a measure
-        // of consistency.
-        let length_offset = 0.35;
-        let length_ret_max = 0.65;
-        let mut length_ret = self.0.len() as f64 / DECK_SIZE as f64;
-        length_ret = 1.0 - (length_ret - length_offset) / length_ret_max;
-        // THIRD SCORE: the number of cards that match the given tags.
-        let mut tags_ret = 0.0;
-        for (idx, qty) in &self.0 {
-            let card = Context::idx_to_card(ctx, *idx);
-            for tag in &ctx.tags {
-                if card.tags_.contains(tag) {
-                    tags_ret += *qty as f64;
-                    break;
-                }
-            }
-        }
-        tags_ret /= DECK_SIZE as f64;
-        curve_score * w_curve + length_ret * w_length + tags_ret * w_tags
+    // Its value is a weighed sum of sub-scores, see the fitness module.
+    fn rate(&self, ctx: &Context, fitness: &Fitness<Self>) -> f64 {
+        fitness.rate(ctx, self)
+    }
+
+    // The unweighted sub-scores, for when they are optimized as separate objectives.
+    fn objectives(&self, ctx: &Context, fitness: &Fitness<Self>) -> Vec<f64> {
+        fitness.scores(ctx, self)
     }
 
     // A copy of random card (with CardList index i) is replaced by another extracted from the slice
@@ -267,12 +294,59 @@ impl Deck for DeckBTree {
         }
         panic!("Failed to reach deck size.")
     }
+
+    // The number of card copies that have to be swapped out to turn this deck into the other.
+    // NOTE: assumes that both decks are full.
+    fn distance(&self, other: &DeckBTree) -> i8 {
+        let mut ret = 0;
+        for (idx, qty) in &self.0 {
+            ret += max(0, qty - other.0.get(idx).unwrap_or(&0));
+        }
+        ret
+    }
+
+    // How differently the decks are spread over the tags, from 0 to 1 (see distance::tags). Unlike
+    // distance, it tells whether decks with different cards still play the same archetype.
+    fn tag_distance(&self, ctx: &Context, other: &DeckBTree) -> f64 {
+        distance::tags(ctx, self, other)
+    }
+
+    // Swaps a random copy of a card that the other deck has fewer copies of with a random copy of a
+    // card that this deck has fewer copies of, reducing the distance between the two by one.
+    // NOTE: assumes that both decks are full.
+    fn approach(&mut self, other: &DeckBTree) {
+        fn excess(a: &DeckBTree, b: &DeckBTree) -> Vec<usize> {
+            a.0.iter()
+                .filter(|(idx, qty)| **qty > *b.0.get(idx).unwrap_or(&0))
+                .map(|(idx, _)| *idx)
+                .collect()
+        }
+        let extra = excess(self, other);
+        let missing = excess(other, self);
+        if extra.is_empty() || missing.is_empty() { return; }
+        self.cut(extra[rand::thread_rng().gen_range(0, extra.len())]);
+        self.add(missing[rand::thread_rng().gen_range(0, missing.len())]);
+    }
+}
+
+// A deck of the most copies of each card that passes the filter, in cardpool order, stopping at
+// the deck size. It can be short of it if too few cards pass.
+#[cfg(test)]
+pub(crate) fn deck_of_cards(ctx: &Context, keep: impl Fn(&CardInfo) -> bool) -> DeckBTree {
+    let mut ret = DeckBTree::new();
+    for idx in (0..ctx.cards_vec.0.len()).filter(|i| keep(ctx.idx_to_card(*i))) {
+        while ret.len() < DECK_SIZE && ret.0.get(&idx).is_none_or(|qty| *qty < MAX_QTY) {
+            ret.add(idx);
+        }
+    }
+    ret
 }
 
 #[cfg(test)]
 mod tests {
     use crate::context::Context;
-    use crate::deck::{Deck, DECK_SIZE, DeckBTree, MAX_QTY};
+    use crate::deck::{Deck, DECK_SIZE, DeckBTree, MAX_QTY, pp_bucket};
+    use crate::fitness::Fitness;
 
     #[test]
     fn add_and_len() {
@@ -282,6 +356,20 @@ mod tests {
         assert_eq!(d.len(), MAX_QTY);
     }
 
+    #[test]
+    fn key() {
+        let mut d1 = DeckBTree::new();
+        let mut d2 = DeckBTree::new();
+        for i in 0..DECK_SIZE as usize {
+            d1.add(i);
+            d2.add(DECK_SIZE as usize - 1 - i);
+        }
+        assert_eq!(d1.key(), d2.key());
+        d2.cut(0);
+        d2.add(DECK_SIZE as usize);
+        assert_ne!(d1.key(), d2.key());
+    }
+
     #[test]
     fn t_rand_idx() {
         let ctx = Context::from_debug();
@@ -340,6 +428,24 @@ mod tests {
         assert_eq!(d.len(), 1)
     }
 
+    #[test]
+    fn repair() {
+        let ctx = Context::from_debug();
+        let mut d = DeckBTree::new();
+        for i in 0..20 {
+            for _ in 0..MAX_QTY {
+                d.add(i);
+            }
+        }
+        d.repair(&ctx);
+        assert_eq!(d.len(), DECK_SIZE);
+        let mut d = DeckBTree::new();
+        d.add(0);
+        d.repair(&ctx);
+        assert_eq!(d.len(), DECK_SIZE);
+        assert!(d.0.contains_key(&0));
+    }
+
     #[test]
     fn deck_info() {
         // Rune has both 0pps and >10pps to test index errors.
@@ -357,6 +463,29 @@ mod tests {
         println!("{}", d.url(&ctx));
     }
 
+    #[test]
+    fn effective_curve() {
+        let ctx = Context::from_debug();
+        let d = DeckBTree::from_rand(&ctx);
+        let curve = d.effective_pp_curve(&ctx);
+        assert!(f64::abs(curve.iter().sum::<f64>() - DECK_SIZE as f64) < 1e-9);
+        // a card with an alternate cost is split between the two buckets.
+        let idx = (0..ctx.cards_vec.0.len())
+            .find(|i| ctx.idx_to_card(*i).alt_costs_.len() == 1)
+            .unwrap();
+        let card = ctx.idx_to_card(idx);
+        let mut d = DeckBTree::new();
+        d.add(idx);
+        d.add(idx);
+        let curve = d.effective_pp_curve(&ctx);
+        let (base, alt) = (pp_bucket(card.pp_), pp_bucket(card.alt_costs_[0].1));
+        if base != alt {
+            assert_eq!((curve[base], curve[alt]), (1.0, 1.0));
+        } else {
+            assert_eq!(curve[base], 2.0);
+        }
+    }
+
     #[test]
     fn rate_curve() {
         // testing with dragon, which has 40 10+pp cards
@@ -369,7 +498,7 @@ mod tests {
             }
         }
         d.add(ctx.cards_vec.0.len() - 14);
-        assert!(d.rate(&ctx, 1.0, 0.0, 0.0) < 0.0001);
+        assert!(d.rate(&ctx, &Fitness::from_weights(1.0, 0.0, 0.0)) < 0.0001);
         let mut d = DeckBTree::new();
         let cmp_arr: [usize; 8] = [4, 14, 6, 5, 4, 3, 2, 2];
         for pp in 0..8 {
@@ -383,7 +512,7 @@ mod tests {
                 d.add(curr[i]);
             }
         }
-        assert!(f64::abs(1.0 - d.rate(&ctx, 1.0, 0.0, 0.0)) < 0.0001);
+        assert!(f64::abs(1.0 - d.rate(&ctx, &Fitness::from_weights(1.0, 0.0, 0.0))) < 0.0001);
     }
 
     #[test]
@@ -393,7 +522,7 @@ mod tests {
         for i in 0..DECK_SIZE {
             d.add(i as usize);
         }
-        assert_eq!(0.0, d.rate(&ctx, 0.0, 1.0, 0.0));
+        assert_eq!(0.0, d.rate(&ctx, &Fitness::from_weights(0.0, 0.0, 1.0)));
         let mut d = DeckBTree::new();
         for i in 0..13 {
             for _ in 0..3 {
@@ -401,7 +530,7 @@ mod tests {
             }
         }
         d.add(13);
-        assert_eq!(1.0, d.rate(&ctx, 0.0, 1.0, 0.0));
+        assert_eq!(1.0, d.rate(&ctx, &Fitness::from_weights(0.0, 0.0, 1.0)));
     }
 
     #[test]
@@ -417,7 +546,7 @@ mod tests {
         // sorting by tags puts tagless cards first, and there's enough of them for a deck,
         // so the score should be very low
         dbg!(&d);
-        assert!(d.rate(&ctx, 0.0, 0.0, 1.0) < 0.1);
+        assert!(d.rate(&ctx, &Fitness::from_weights(0.0, 1.0, 0.0)) < 0.1);
         d = DeckBTree::new();
         for i in ctx.cards_vec.0.len() - 14..ctx.cards_vec.0.len() - 1 {
             d.add(i);
@@ -427,7 +556,7 @@ mod tests {
         d.add(ctx.cards_vec.0.len() - 1);
         // on the other hand near the end of the list, both because higher pp cards have more effect
         // and because of tagless cards came first, we expect a high concentration of tagged cards.
-        assert!(d.rate(&ctx, 0.0, 0.0, 1.0) > 0.6);
+        assert!(d.rate(&ctx, &Fitness::from_weights(0.0, 1.0, 0.0)) > 0.6);
         println!("{}", d.url(&ctx));
     }
 
@@ -477,4 +606,40 @@ mod tests {
             assert!(d1.0.contains_key(key) | d2.0.contains_key(key));
         }
     }
+
+    #[test]
+    fn distance() {
+        let ctx = Context::from_debug();
+        let d1 = DeckBTree::from_rand(&ctx);
+        assert_eq!(d1.distance(&d1), 0);
+        let mut d2 = d1.clone();
+        d2.mutate(&ctx, 3, 3, 0.5, 1.0);
+        // a mutation swaps at most one card copy
+        assert!(d1.distance(&d2) <= 1);
+        assert_eq!(d1.distance(&d2), d2.distance(&d1));
+        let mut d3 = DeckBTree::new();
+        for i in 0..DECK_SIZE {
+            d3.add(ctx.cards_vec.0.len() - 1 - i as usize);
+        }
+        let mut d4 = DeckBTree::new();
+        for i in 0..DECK_SIZE {
+            d4.add(i as usize);
+        }
+        assert_eq!(d3.distance(&d4), DECK_SIZE);
+    }
+
+    #[test]
+    fn approach() {
+        let ctx = Context::from_debug();
+        let d1 = DeckBTree::from_rand(&ctx);
+        let mut d2 = DeckBTree::from_rand(&ctx);
+        let distance = d1.distance(&d2);
+        d2.approach(&d1);
+        assert_eq!(d2.len(), DECK_SIZE);
+        assert_eq!(d1.distance(&d2), distance - 1);
+        for _ in 0..DECK_SIZE {
+            d2.approach(&d1);
+        }
+        assert_eq!(d1.distance(&d2), 0);
+    }
 }
\ No newline at end of file
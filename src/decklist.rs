@@ -0,0 +1,258 @@
+use std::fs;
+use std::io;
+
+use crate::context::Context;
+use crate::deck::{Deck, DeckBTree};
+
+const RADIX: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz-_";
+
+// Reverses the encoding done by Deck::url.
+pub fn from_url(ctx: &Context, url: &str) -> Result<DeckBTree, String> {
+    let hash = url.trim()
+        .split("/deck/").nth(1).ok_or(format!("not a deck url: {}", url))?
+        .split('?').next().unwrap();
+    let mut fields = hash.split('.');
+    let _game_mode = fields.next();
+    let craft: usize = fields.next().and_then(|c| c.parse().ok())
+        .ok_or(format!("not a deck url: {}", url))?;
+    if craft != ctx.craft + 1 {
+        return Err(format!("the deck isn't for the chosen craft: {}", url));
+    }
+    let mut ret = DeckBTree::new();
+    let mut unresolved = vec![];
+    for card_hash in fields.filter(|f| !f.is_empty()) {
+        let mut id = 0;
+        for c in card_hash.chars() {
+            let digit = RADIX.find(c).ok_or(format!("invalid card hash {}", card_hash))?;
+            id = id * 64 + digit as i32;
+        }
+        match ctx.id_to_idx(id) {
+            Some(idx) => ret.add(idx),
+            None => unresolved.push(id.to_string()),
+        }
+    }
+    if !unresolved.is_empty() {
+        return Err(format!("cards not in the cardpool: {}", unresolved.join(", ")));
+    }
+    Ok(ret)
+}
+
+// The number of single character edits needed to turn a into b (Levenshtein distance).
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + (ca != *cb) as usize;
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+// The most typos tolerated in a card name, as a fraction of its length.
+const TYPO_TOLERANCE: f64 = 0.2;
+
+// The index of the card with the given name, ignoring case, and whether the name had to be
+// corrected: when no name matches exactly, the closest one is taken if it's close enough and no
+// other is as close.
+// NOTE: reprints share the name of the original card, the first of them in the cardpool is taken.
+pub(crate) fn find_card(ctx: &Context, name: &str) -> Result<(usize, bool), String> {
+    let name = name.to_lowercase();
+    let names: Vec<String> = (0..ctx.cards_vec.0.len())
+        .map(|i| ctx.idx_to_card(i).name_.to_lowercase())
+        .collect();
+    if let Some(idx) = names.iter().position(|n| *n == name) {
+        return Ok((idx, false));
+    }
+    let distances: Vec<usize> = names.iter().map(|n| edit_distance(&name, n)).collect();
+    let best = *distances.iter().min().ok_or(name.clone())?;
+    let closest: Vec<usize> = (0..names.len()).filter(|i| distances[*i] == best).collect();
+    let tolerance = ((name.chars().count() as f64 * TYPO_TOLERANCE) as usize).max(1);
+    if best <= tolerance && closest.iter().all(|i| names[*i] == names[closest[0]]) {
+        return Ok((closest[0], true));
+    }
+    let mut suggestions: Vec<&str> = closest.iter()
+        .map(|i| ctx.idx_to_card(*i).name_.as_str())
+        .collect();
+    suggestions.dedup();
+    suggestions.truncate(3);
+    Err(format!("{} (closest: {})", name, suggestions.join(" / ")))
+}
+
+// Parses a list of "3x Card Name" lines. Names are matched case-insensitively, and tolerating
+// some typos: the corrected names are returned along with the deck, as "typo" -> "Name" lines.
+// The "[idx]" prefix of the lines of Deck::as_string is skipped, so that its list can be read back.
+pub fn from_list(ctx: &Context, list: &str) -> Result<(DeckBTree, Vec<String>), String> {
+    let mut ret = DeckBTree::new();
+    let mut corrections = vec![];
+    let mut unresolved = vec![];
+    for line in list.lines().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+        let line = match line.strip_prefix('[') {
+            Some(rest) => rest.split_once(']').ok_or(format!("invalid line: {}", line))?.1.trim(),
+            None => line,
+        };
+        let (qty, name) = line.split_once(' ').ok_or(format!("invalid line: {}", line))?;
+        let qty: i8 = qty.trim_end_matches(['x', 'X']).parse()
+            .map_err(|_| format!("invalid line: {}", line))?;
+        match find_card(ctx, name.trim()) {
+            Ok((idx, corrected)) => {
+                if corrected {
+                    let card = &ctx.idx_to_card(idx).name_;
+                    corrections.push(format!("\"{}\" -> \"{}\"", name.trim(), card));
+                }
+                for _ in 0..qty { ret.add(idx) }
+            }
+            Err(e) => unresolved.push(e),
+        }
+    }
+    if !unresolved.is_empty() {
+        return Err(format!("cards not in the cardpool: {}", unresolved.join(", ")));
+    }
+    Ok((ret, corrections))
+}
+
+// The deck as "3x Card Name" lines, which from_list reads back.
+pub fn to_list(ctx: &Context, deck: &DeckBTree) -> String {
+    deck.0.iter()
+        .map(|(idx, qty)| format!("{}x {}\n", qty, ctx.idx_to_card(*idx).name_))
+        .collect()
+}
+
+// Reads a file of decks, each being either a deck url on its own line, or a decklist.
+// Decklists are separated by blank lines. Decks that can't be read are skipped with a warning,
+// the others are made legal if they aren't. The card names corrected in the decklists are
+// returned too (see from_list).
+pub fn read_decks(ctx: &Context, path: &str) -> Result<(Vec<DeckBTree>, Vec<String>), io::Error> {
+    let text = fs::read_to_string(path)?;
+    let mut blocks = vec![vec![]];
+    for line in text.lines().map(|l| l.trim()) {
+        if line.is_empty() {
+            blocks.push(vec![]);
+        } else {
+            blocks.last_mut().unwrap().push(line);
+        }
+    }
+    let (mut ret, mut corrections) = (vec![], vec![]);
+    for block in blocks {
+        let (urls, list): (Vec<&str>, Vec<&str>) = block.into_iter()
+            .partition(|l| l.contains("/deck/"));
+        let mut decks: Vec<Result<DeckBTree, String>> = urls.iter()
+            .map(|url| from_url(ctx, url))
+            .collect();
+        if !list.is_empty() {
+            decks.push(from_list(ctx, &list.join("\n")).map(|(deck, c)| {
+                corrections.extend(c);
+                deck
+            }));
+        }
+        for deck in decks {
+            match deck {
+                Ok(mut deck) => {
+                    deck.repair(ctx);
+                    ret.push(deck);
+                }
+                Err(e) => println!("Skipping a deck: {}", e),
+            }
+        }
+    }
+    Ok((ret, corrections))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::env;
+    use std::fs;
+
+    use crate::context::Context;
+    use crate::deck::{Deck, DECK_SIZE, DeckBTree};
+    use crate::decklist::{edit_distance, from_list, from_url, read_decks, to_list};
+
+    #[test]
+    fn url() {
+        let ctx = Context::from_debug();
+        let d = DeckBTree::from_rand(&ctx);
+        let d2 = from_url(&ctx, &d.url(&ctx)).unwrap();
+        assert_eq!(d.distance(&d2), 0);
+        // the portal appends the language to the urls.
+        let d2 = from_url(&ctx, &(d.url(&ctx) + "?lang=en")).unwrap();
+        assert_eq!(d.distance(&d2), 0);
+        // a deck for another craft.
+        assert!(from_url(&ctx, "https://shadowverse-portal.com/deck/1.1.6lZu2").is_err());
+        assert!(from_url(&ctx, "https://shadowverse-portal.com/").is_err());
+    }
+
+    #[test]
+    fn list() {
+        let ctx = Context::from_debug();
+        let first = ctx.idx_to_card(0).name_.clone();
+        let second = ctx.idx_to_card(1).name_.to_uppercase();
+        let (d, corrections) = from_list(&ctx, &format!("3x {}\n\n2 {}\n", first, second)).unwrap();
+        assert!(corrections.is_empty());
+        assert_eq!(d.0[&0], 3);
+        assert_eq!(d.0[&1], 2);
+        assert!(from_list(&ctx, "3x Not A Card").is_err());
+        assert!(from_list(&ctx, "three copies").is_err());
+    }
+
+    #[test]
+    fn typos() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("abc", "abc"), 0);
+        let ctx = Context::from_debug();
+        let (idx, name) = (0..ctx.cards_vec.0.len())
+            .map(|i| (i, ctx.idx_to_card(i).name_.clone()))
+            .find(|(_, n)| n.len() > 10)
+            .unwrap();
+        // a letter dropped.
+        let typo: String = name.chars().skip(1).collect();
+        let (d, corrections) = from_list(&ctx, &format!("2x {}", typo)).unwrap();
+        assert_eq!(d.0[&idx], 2);
+        assert_eq!(corrections, vec![format!("\"{}\" -> \"{}\"", typo, name)]);
+        // the unresolved names are reported.
+        let e = from_list(&ctx, "3x Not A Card\n1x Neither Is This").unwrap_err();
+        assert!(e.contains("not a card") && e.contains("neither is this"));
+    }
+
+    #[test]
+    fn round_trip() {
+        let ctx = Context::from_debug();
+        // reprints can't be told apart by name, so decks are compared by the copies of each name.
+        let names = |d: &DeckBTree| {
+            let mut ret = BTreeMap::new();
+            for (idx, qty) in &d.0 {
+                *ret.entry(ctx.idx_to_card(*idx).name_.clone()).or_insert(0) += qty;
+            }
+            ret
+        };
+        for _ in 0..10 {
+            let d = DeckBTree::from_rand(&ctx);
+            assert_eq!(names(&from_list(&ctx, &to_list(&ctx, &d)).unwrap().0), names(&d));
+            // the list part of as_string, after the histogram.
+            let s = d.as_string(&ctx);
+            let list = s.split("+\n").nth(1).unwrap();
+            assert_eq!(names(&from_list(&ctx, list).unwrap().0), names(&d));
+        }
+    }
+
+    #[test]
+    fn file() {
+        let ctx = Context::from_debug();
+        let d = DeckBTree::from_rand(&ctx);
+        let path = env::temp_dir().join("shadowgen_read_decks.txt");
+        let text = format!("{}\n{}\n\n1x {}\n\n3x Not A Card\n",
+                           d.url(&ctx), d.url(&ctx), ctx.idx_to_card(0).name_);
+        fs::write(&path, text).unwrap();
+        let (decks, corrections) = read_decks(&ctx, path.to_str().unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(decks.len(), 3);
+        assert!(corrections.is_empty());
+        assert_eq!(decks[0].distance(&d), 0);
+        assert!(decks.iter().all(|d| d.len() == DECK_SIZE));
+    }
+}
@@ -0,0 +1,91 @@
+use std::collections::BTreeSet;
+
+use crate::context::Context;
+use crate::deck::{Deck, DeckBTree, hist, PP_CURVE_SIZE};
+use crate::distance::tag_profile;
+use crate::upgrade::{Copies, swaps};
+
+// Two histograms next to each other, aligned on their x axis.
+fn side_by_side(left: &str, right: &str) -> String {
+    let left: Vec<&str> = left.lines().filter(|l| !l.is_empty()).collect();
+    let right: Vec<&str> = right.lines().filter(|l| !l.is_empty()).collect();
+    let width = left.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+    let height = left.len().max(right.len());
+    // the ith line of a histogram padded on top to the given height.
+    fn pad<'a>(lines: &[&'a str], height: usize, i: usize) -> &'a str {
+        if i < height - lines.len() { "" } else { lines[i - (height - lines.len())] }
+    }
+    let mut ret = String::new();
+    for i in 0..height {
+        let l = pad(&left, height, i);
+        let line = format!("{}{}    {}", l, " ".repeat(width - l.chars().count()),
+                           pad(&right, height, i));
+        ret.push_str(line.trim_end());
+        ret.push('\n');
+    }
+    ret
+}
+
+fn copies_as_string(ctx: &Context, copies: &Copies, sign: char) -> String {
+    let ret: Vec<String> = copies.iter()
+        .map(|(idx, qty)| format!("{}{} {}", sign, qty, ctx.idx_to_card(*idx).name_))
+        .collect();
+    if ret.is_empty() { String::from("none") } else { ret.join(", ") }
+}
+
+// What changes from deck a to deck b: the cards removed and added, the pp curves of both with the
+// difference in each bucket, and how the share of each tag moves.
+pub fn diff(ctx: &Context, a: &DeckBTree, b: &DeckBTree) -> String {
+    let (removed, added) = swaps(a, b);
+    let mut ret = format!("Removed: {}\nAdded: {}\n",
+                          copies_as_string(ctx, &removed, '-'),
+                          copies_as_string(ctx, &added, '+'));
+    let (curve_a, curve_b) = (a.pp_curve(ctx), b.pp_curve(ctx));
+    ret.push_str(&side_by_side(&hist(&curve_a, "#", 1), &hist(&curve_b, "#", 1)));
+    let deltas: Vec<String> = (0..PP_CURVE_SIZE)
+        .map(|i| format!("{}: {:+}", if i == 0 { String::from("0/1") } else { (i + 1).to_string() },
+                         curve_b[i] - curve_a[i]))
+        .collect();
+    ret.push_str(&format!("PP curve delta: {}\n", deltas.join(", ")));
+    let (tags_a, tags_b) = (tag_profile(ctx, a), tag_profile(ctx, b));
+    let tags: BTreeSet<&str> = tags_a.keys().chain(tags_b.keys()).copied().collect();
+    ret.push_str("Tags:\n");
+    for tag in tags {
+        let (before, after) = (*tags_a.get(tag).unwrap_or(&0.0), *tags_b.get(tag).unwrap_or(&0.0));
+        ret.push_str(&format!("\t{}: {:.1}% -> {:.1}% ({:+.1}%)\n",
+                              tag, 100.0 * before, 100.0 * after, 100.0 * (after - before)));
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::Context;
+    use crate::deck::{Deck, DeckBTree, PP_CURVE_SIZE};
+    use crate::diff::{diff, side_by_side};
+
+    #[test]
+    fn histograms() {
+        let s = side_by_side("\n#\n##\n", "\n$$\n");
+        assert_eq!(s, "#\n##    $$\n");
+    }
+
+    #[test]
+    fn deck_diff() {
+        let ctx = Context::from_debug();
+        let d = DeckBTree::from_rand(&ctx);
+        let same = diff(&ctx, &d, &d);
+        assert!(same.starts_with("Removed: none\nAdded: none\n"));
+        assert!(same.contains(&format!("PP curve delta: 0/1: +0, {}",
+                                       (2..=PP_CURVE_SIZE).map(|i| format!("{}: +0", i))
+                                           .collect::<Vec<String>>().join(", "))));
+        let mut d2 = d.clone();
+        let (idx, _) = d.0.iter().next().unwrap();
+        d2.cut(*idx);
+        let new = (0..ctx.cards_vec.0.len()).find(|i| !d.0.contains_key(i)).unwrap();
+        d2.add(new);
+        let changed = diff(&ctx, &d, &d2);
+        let name = |i: usize| ctx.idx_to_card(i).name_.clone();
+        assert!(changed.starts_with(&format!("Removed: -1 {}\nAdded: +1 {}\n", name(*idx), name(new))));
+    }
+}
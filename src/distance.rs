@@ -0,0 +1,106 @@
+use fnv::FnvHashMap;
+
+use crate::context::Context;
+use crate::deck::DeckBTree;
+
+// The copies of each card in either deck.
+fn pairs<'a>(a: &'a DeckBTree, b: &'a DeckBTree) -> impl Iterator<Item=(i8, i8)> + 'a {
+    let only_b = b.0.iter().filter(move |(idx, _)| !a.0.contains_key(idx)).map(|(_, qty)| (0, *qty));
+    a.0.iter().map(move |(idx, qty)| (*qty, *b.0.get(idx).unwrap_or(&0))).chain(only_b)
+}
+
+// One minus the Jaccard index of the decks seen as multisets of cards: 0 for the same deck, 1 for
+// decks with no cards in common.
+pub fn jaccard(a: &DeckBTree, b: &DeckBTree) -> f64 {
+    let (mut common, mut all) = (0, 0);
+    for (qa, qb) in pairs(a, b) {
+        common += qa.min(qb) as i32;
+        all += qa.max(qb) as i32;
+    }
+    if all == 0 { 0.0 } else { 1.0 - common as f64 / all as f64 }
+}
+
+// The total difference in the number of copies of each card. Between decks of the same size this
+// is twice the number of swaps that turn one into the other (see Deck::distance).
+pub fn l1(a: &DeckBTree, b: &DeckBTree) -> i32 {
+    pairs(a, b).map(|(qa, qb)| (qa - qb).abs() as i32).sum()
+}
+
+// The fraction of the tags of the deck's cards that each tag makes up.
+pub(crate) fn tag_profile<'a>(ctx: &'a Context, deck: &DeckBTree) -> FnvHashMap<&'a str, f64> {
+    let mut ret = FnvHashMap::default();
+    let mut total = 0.0;
+    for (idx, qty) in &deck.0 {
+        for tag in &ctx.idx_to_card(*idx).tags_ {
+            *ret.entry(tag.as_str()).or_insert(0.0) += *qty as f64;
+            total += *qty as f64;
+        }
+    }
+    ret.values_mut().for_each(|v| *v /= total);
+    ret
+}
+
+// How differently the decks are spread over the archetypes, regardless of the actual cards: the
+// total variation distance between their tag profiles, from 0 to 1.
+pub fn tags(ctx: &Context, a: &DeckBTree, b: &DeckBTree) -> f64 {
+    let (pa, pb) = (tag_profile(ctx, a), tag_profile(ctx, b));
+    if pa.is_empty() || pb.is_empty() {
+        return if pa.is_empty() && pb.is_empty() { 0.0 } else { 1.0 };
+    }
+    let only_b: f64 = pb.iter().filter(|(tag, _)| !pa.contains_key(*tag)).map(|(_, v)| v).sum();
+    let rest: f64 = pa.iter().map(|(tag, v)| (v - pb.get(tag).unwrap_or(&0.0)).abs()).sum();
+    (only_b + rest) / 2.0
+}
+
+// The mean distance between every pair of the decks, by each metric.
+pub fn summary(ctx: &Context, decks: &[DeckBTree]) -> String {
+    let (mut sums, mut n) = ((0.0, 0.0, 0.0), 0.0);
+    for (i, a) in decks.iter().enumerate() {
+        for b in &decks[i + 1..] {
+            sums.0 += jaccard(a, b);
+            sums.1 += l1(a, b) as f64;
+            sums.2 += tags(ctx, a, b);
+            n += 1.0;
+        }
+    }
+    if n == 0.0 { return String::from("Mean pairwise distance: not enough decks"); }
+    format!("Mean pairwise distance - jaccard: {:.4}, copies: {:.2}, tags: {:.4}",
+            sums.0 / n, sums.1 / n, sums.2 / n)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::Context;
+    use crate::deck::{Deck, DeckBTree};
+    use crate::distance::{jaccard, l1, summary, tags};
+
+    fn deck(cards: &[(usize, i8)]) -> DeckBTree {
+        let mut ret = DeckBTree::new();
+        for (idx, qty) in cards {
+            for _ in 0..*qty {
+                ret.add(*idx);
+            }
+        }
+        ret
+    }
+
+    #[test]
+    fn metrics() {
+        let ctx = Context::from_debug();
+        let a = deck(&[(0, 3), (1, 1)]);
+        let b = deck(&[(0, 1), (2, 3)]);
+        // in common: 1 copy, in either: 3 + 1 + 3.
+        assert!(f64::abs(jaccard(&a, &b) - (1.0 - 1.0 / 7.0)) < 1e-9);
+        assert_eq!(l1(&a, &b), 2 + 1 + 3);
+        assert_eq!(jaccard(&a, &a), 0.0);
+        assert_eq!(jaccard(&a, &deck(&[(3, 3)])), 1.0);
+        assert_eq!(l1(&a, &a), 0);
+        assert_eq!(tags(&ctx, &a, &a), 0.0);
+        let (d1, d2) = (DeckBTree::from_rand(&ctx), DeckBTree::from_rand(&ctx));
+        assert_eq!(l1(&d1, &d2), 2 * d1.distance(&d2) as i32);
+        let t = tags(&ctx, &d1, &d2);
+        assert!((0.0..=1.0).contains(&t));
+        assert!(f64::abs(t - tags(&ctx, &d2, &d1)) < 1e-9);
+        assert!(summary(&ctx, &[d1, d2]).starts_with("Mean pairwise distance - jaccard"));
+    }
+}
@@ -0,0 +1,82 @@
+use crate::context::Context;
+use crate::deck::{Deck, DECK_SIZE, DeckBTree, MAX_QTY};
+use crate::fitness::{Fitness, LinearForm};
+
+// Finds the provably best deck for a linear fitness.
+// A linear score is a sum of independent contributions of each card, which only depend on its
+// number of copies, so the problem is a knapsack: the best deck of k cards using the first i
+// cards of the pool is the best, over the number of copies x of the ith card, of the best deck of
+// k - x cards using the first i - 1 cards, plus the contribution of x copies.
+// Returns None if the fitness isn't linear.
+pub fn solve(ctx: &Context, fitness: &Fitness) -> Option<(DeckBTree, f64)> {
+    let form = fitness.linear(ctx)?;
+    let (deck, score) = solve_linear(&form);
+    Some((deck, score))
+}
+
+fn solve_linear(form: &LinearForm) -> (DeckBTree, f64) {
+    let size = DECK_SIZE as usize;
+    // best[k]: the best score of a deck of k cards among the cards considered so far.
+    let mut best = vec![f64::NEG_INFINITY; size + 1];
+    best[0] = form.constant;
+    // copies[i][k]: the number of copies of the card i in the best deck of k cards.
+    let mut copies = vec![vec![0; size + 1]; form.per_copy.len()];
+    for (i, per_copy) in form.per_copy.iter().enumerate() {
+        let mut next = best.clone();
+        for k in 1..=size {
+            for x in 1..=(MAX_QTY as usize).min(k) {
+                let score = best[k - x] + per_copy * x as f64 + form.per_card;
+                if score > next[k] {
+                    next[k] = score;
+                    copies[i][k] = x;
+                }
+            }
+        }
+        best = next;
+    }
+    // walking back through the choices.
+    let mut ret = DeckBTree::new();
+    let mut k = size;
+    for i in (0..form.per_copy.len()).rev() {
+        for _ in 0..copies[i][k] {
+            ret.add(i);
+        }
+        k -= copies[i][k];
+    }
+    (ret, best[size])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::Context;
+    use crate::deck::{Deck, DECK_SIZE, DeckBTree};
+    use crate::exact::{solve, solve_linear};
+    use crate::fitness::{Fitness, LinearForm};
+
+    #[test]
+    fn knapsack() {
+        let mut form = LinearForm { constant: 0.0, per_copy: vec![0.0; 20], per_card: -1.0 };
+        form.per_copy[5] = 2.0;
+        form.per_copy[7] = 1.0;
+        let (d, score) = solve_linear(&form);
+        assert_eq!(d.len(), DECK_SIZE);
+        assert_eq!(d.0[&5], 3);
+        assert_eq!(d.0[&7], 3);
+        // 14 cards are needed for 40 copies.
+        assert_eq!(d.0.len(), 14);
+        assert_eq!(score, 3.0 * 2.0 + 3.0 * 1.0 - 14.0);
+    }
+
+    #[test]
+    fn optimum() {
+        let ctx = Context::from_debug();
+        let f = Fitness::from_weights(0.0, 0.6, 0.4);
+        let (d, score) = solve(&ctx, &f).unwrap();
+        assert_eq!(d.len(), DECK_SIZE);
+        assert!(f64::abs(d.rate(&ctx, &f) - score) < 1e-9);
+        for _ in 0..100 {
+            assert!(DeckBTree::from_rand(&ctx).rate(&ctx, &f) <= score);
+        }
+        assert!(solve(&ctx, &Fitness::from_weights(1.0, 0.0, 0.0)).is_none());
+    }
+}
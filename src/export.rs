@@ -0,0 +1,143 @@
+use std::fs;
+use std::io;
+
+use serde_json::{json, Map, Value};
+
+use crate::context::Context;
+use crate::deck::{Deck, DeckBTree};
+use crate::fitness::Fitness;
+
+// The columns of the cards table of the CSV export.
+const CARD_COLUMNS: [&str; 8] = ["id", "name", "craft", "pp", "type", "trait", "tags", "copies"];
+
+// The deck with everything known about it: the format and craft it's for, its url, its score and
+// the score of each fitness component, and the metadata of each card.
+pub fn to_json(ctx: &Context, fitness: &Fitness, deck: &DeckBTree) -> Value {
+    let components: Map<String, Value> = fitness.names().into_iter()
+        .zip(deck.objectives(ctx, fitness))
+        .map(|(name, score)| (name, json!(score)))
+        .collect();
+    let cards: Vec<Value> = deck.0.iter().map(|(idx, qty)| {
+        let card = ctx.idx_to_card(*idx);
+        json!({
+            "id": card.id_,
+            "name": card.name_,
+            "craft": card.craft_,
+            "pp": card.pp_,
+            "type": card.type_,
+            "trait": card.trait_,
+            "tags": card.tags_,
+            "copies": qty,
+        })
+    }).collect();
+    json!({
+        "format": ctx.game_mode_name(),
+        "craft": ctx.craft_name(),
+        "url": deck.url(ctx),
+        "fitness": deck.rate(ctx, fitness),
+        "components": components,
+        "cards": cards,
+    })
+}
+
+// Quotes the field if it would otherwise break the row.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn csv_row(fields: &[String]) -> String {
+    fields.iter().map(|f| csv_field(f)).collect::<Vec<String>>().join(",") + "\n"
+}
+
+// The same contents as the JSON export, as a sheet: a "key,value" row for each property of the
+// deck, a blank line, then a row for each card. Tags are separated by semicolons.
+pub fn to_csv(ctx: &Context, fitness: &Fitness, deck: &DeckBTree) -> String {
+    let mut ret = String::new();
+    let mut properties = vec![
+        (String::from("format"), ctx.game_mode_name().to_string()),
+        (String::from("craft"), ctx.craft_name().to_string()),
+        (String::from("url"), deck.url(ctx)),
+        (String::from("fitness"), deck.rate(ctx, fitness).to_string()),
+    ];
+    for (name, score) in fitness.names().into_iter().zip(deck.objectives(ctx, fitness)) {
+        properties.push((name, score.to_string()));
+    }
+    for (key, value) in properties {
+        ret.push_str(&csv_row(&[key, value]));
+    }
+    ret.push('\n');
+    ret.push_str(&csv_row(&CARD_COLUMNS.map(String::from)));
+    for (idx, qty) in &deck.0 {
+        let card = ctx.idx_to_card(*idx);
+        ret.push_str(&csv_row(&[
+            card.id_.to_string(),
+            card.name_.clone(),
+            card.craft_.clone(),
+            card.pp_.to_string(),
+            card.type_.clone(),
+            card.trait_.clone(),
+            card.tags_.join(";"),
+            qty.to_string(),
+        ]));
+    }
+    ret
+}
+
+// Writes the deck to the file, as CSV if its extension is .csv and as JSON otherwise.
+pub fn export(ctx: &Context, fitness: &Fitness, deck: &DeckBTree, path: &str) -> Result<(), io::Error> {
+    let text = if path.ends_with(".csv") {
+        to_csv(ctx, fitness, deck)
+    } else {
+        serde_json::to_string_pretty(&to_json(ctx, fitness, deck))?
+    };
+    fs::write(path, text)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::Context;
+    use crate::deck::{Deck, DeckBTree};
+    use crate::export::{csv_field, to_csv, to_json};
+    use crate::fitness::Fitness;
+
+    #[test]
+    fn json() {
+        let ctx = Context::from_debug();
+        let d = DeckBTree::from_rand(&ctx);
+        let f = Fitness::from_weights(0.4, 0.4, 0.2);
+        let j = to_json(&ctx, &f, &d);
+        assert_eq!(j["craft"], "Swordcraft");
+        assert_eq!(j["format"], "Unlimited");
+        assert_eq!(j["url"], d.url(&ctx));
+        assert_eq!(j["fitness"], d.rate(&ctx, &f));
+        assert_eq!(j["components"].as_object().unwrap().len(), 3);
+        let cards = j["cards"].as_array().unwrap();
+        assert_eq!(cards.len(), d.0.len());
+        let copies: i64 = cards.iter().map(|c| c["copies"].as_i64().unwrap()).sum();
+        assert_eq!(copies, d.len() as i64);
+        let (idx, _) = d.0.iter().next().unwrap();
+        assert_eq!(cards[0]["id"], ctx.idx_to_card(*idx).id_);
+    }
+
+    #[test]
+    fn csv() {
+        assert_eq!(csv_field("Urd, Goddess"), "\"Urd, Goddess\"");
+        assert_eq!(csv_field("\"quoted\""), "\"\"\"quoted\"\"\"");
+        assert_eq!(csv_field("plain"), "plain");
+        let ctx = Context::from_debug();
+        let d = DeckBTree::from_rand(&ctx);
+        let f = Fitness::from_weights(0.4, 0.4, 0.2);
+        let csv = to_csv(&ctx, &f, &d);
+        let (properties, cards) = csv.split_once("\n\n").unwrap();
+        // format, craft, url, fitness and the three components.
+        assert_eq!(properties.lines().count(), 7);
+        assert!(properties.starts_with("format,Unlimited\ncraft,Swordcraft\n"));
+        let mut rows = cards.lines();
+        assert_eq!(rows.next().unwrap(), "id,name,craft,pp,type,trait,tags,copies");
+        assert_eq!(rows.count(), d.0.len());
+    }
+}
@@ -0,0 +1,510 @@
+use std::sync::Arc;
+
+use crate::archetype::tag_densities;
+use crate::context::Context;
+use crate::deck::{Deck, DECK_SIZE, DeckBTree, PP_CURVE_SIZE};
+
+// A sub-score of the fitness function. Its value is a float between 0 and 1, the higher the better
+// the deck is. Components that need the cards and copies of the deck are written for DeckBTree,
+// the others for any deck encoding.
+pub trait FitnessComponent<D: Deck = DeckBTree>: Send + Sync {
+    fn name(&self) -> String;
+    fn score(&self, ctx: &Context, deck: &D) -> f64;
+    // The linear form of the score, if there is one. See the exact module.
+    fn linear(&self, _ctx: &Context) -> Option<LinearForm> {
+        None
+    }
+}
+
+// A score that is linear in the number of copies of each card, and in the number of cards with
+// different names: constant + sum(per_copy[i] * copies of i) + per_card * distinct cards.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinearForm {
+    pub constant: f64,
+    // Indexed like CardsVec.
+    pub per_copy: Vec<f64>,
+    pub per_card: f64,
+}
+
+impl LinearForm {
+    pub fn zero(ctx: &Context) -> Self {
+        LinearForm { constant: 0.0, per_copy: vec![0.0; ctx.cards_vec.0.len()], per_card: 0.0 }
+    }
+
+    // Adds weight times the other form.
+    pub fn add(&mut self, other: &LinearForm, weight: f64) {
+        self.constant += weight * other.constant;
+        for (a, b) in self.per_copy.iter_mut().zip(&other.per_copy) {
+            *a += weight * b;
+        }
+        self.per_card += weight * other.per_card;
+    }
+}
+
+// How similar the deck curve is to a given one.
+pub struct Curve {
+    // The curve that the deck should approach.
+    pub ideal: [i32; PP_CURVE_SIZE],
+    // Whether the deck curve is the effective one, where alternate costs count (see Deck).
+    pub effective: bool,
+    // The euclidean norm of the above.
+    ideal_module: f64,
+    // The minimum cosine similarity between the ideal curve and the possible pp curves of a deck,
+    // reached when all of its cards fall in the bucket where the ideal curve is lowest.
+    min_score: f64,
+}
+
+impl Curve {
+    pub fn new(ideal: [i32; PP_CURVE_SIZE]) -> Self {
+        let ideal_module = ideal.iter().map(|i| (i * i) as f64).sum::<f64>().sqrt();
+        let min_score = *ideal.iter().min().unwrap() as f64 / ideal_module;
+        Curve { ideal, effective: false, ideal_module, min_score }
+    }
+}
+
+impl Default for Curve {
+    // An arbitrary curve.
+    fn default() -> Self {
+        Curve::new([4, 14, 6, 5, 4, 3, 2, 2])
+    }
+}
+
+impl<D: Deck> FitnessComponent<D> for Curve {
+    fn name(&self) -> String {
+        String::from("curve")
+    }
+
+    fn score(&self, ctx: &Context, deck: &D) -> f64 {
+        let curve_self = if self.effective {
+            deck.effective_pp_curve(ctx)
+        } else {
+            deck.pp_curve(ctx).map(|qty| qty as f64)
+        };
+        let mut score = 0.0;
+        let mut module_self = 0.0;
+        // Calculating the cosine similarity.
+        for (ideal, qty) in self.ideal.iter().zip(curve_self) {
+            score += (*ideal as f64 * qty).abs();
+            module_self += qty.powi(2);
+        }
+        score /= self.ideal_module * f64::sqrt(module_self);
+        // Normalizing the score between 0 and 1.
+        (score - self.min_score) / (1.0 - self.min_score)
+    }
+}
+
+// The number of cards with different names in the deck, taken as a measure of consistency.
+pub struct Length;
+
+// The fewest cards with different names a deck can have, relative to the deck size, and the range
+// of the possible values.
+const LENGTH_OFFSET: f64 = 0.35;
+const LENGTH_RET_MAX: f64 = 0.65;
+
+impl FitnessComponent for Length {
+    fn name(&self) -> String {
+        String::from("length")
+    }
+
+    fn score(&self, _ctx: &Context, deck: &DeckBTree) -> f64 {
+        let ret = deck.0.len() as f64 / DECK_SIZE as f64;
+        1.0 - (ret - LENGTH_OFFSET) / LENGTH_RET_MAX
+    }
+
+    fn linear(&self, ctx: &Context) -> Option<LinearForm> {
+        let mut ret = LinearForm::zero(ctx);
+        ret.constant = 1.0 + LENGTH_OFFSET / LENGTH_RET_MAX;
+        ret.per_card = -1.0 / (DECK_SIZE as f64 * LENGTH_RET_MAX);
+        Some(ret)
+    }
+}
+
+// The number of cards that match the tags chosen by the user.
+pub struct Tags;
+
+impl FitnessComponent for Tags {
+    fn name(&self) -> String {
+        String::from("tags")
+    }
+
+    fn score(&self, ctx: &Context, deck: &DeckBTree) -> f64 {
+        let mut ret = 0.0;
+        for (idx, qty) in &deck.0 {
+            let card = ctx.idx_to_card(*idx);
+            if ctx.tags.iter().any(|tag| card.tags_.contains(tag)) {
+                ret += *qty as f64;
+            }
+        }
+        ret / DECK_SIZE as f64
+    }
+
+    fn linear(&self, ctx: &Context) -> Option<LinearForm> {
+        let mut ret = LinearForm::zero(ctx);
+        for (idx, coefficient) in ret.per_copy.iter_mut().enumerate() {
+            let card = ctx.idx_to_card(idx);
+            if ctx.tags.iter().any(|tag| card.tags_.contains(tag)) {
+                *coefficient = 1.0 / DECK_SIZE as f64;
+            }
+        }
+        Some(ret)
+    }
+}
+
+// How close the fraction of the deck's copies carrying each tag is to a target, like the densities
+// learned from human decks (see targets.rs). Each tag counts for its distance from the target,
+// relative to the farthest the density could be.
+pub struct TagDensities {
+    pub targets: Vec<(String, f64)>,
+}
+
+impl FitnessComponent for TagDensities {
+    fn name(&self) -> String {
+        String::from("tag densities")
+    }
+
+    fn score(&self, ctx: &Context, deck: &DeckBTree) -> f64 {
+        if self.targets.is_empty() { return 1.0; }
+        let densities = tag_densities(ctx, deck);
+        let distance: f64 = self.targets.iter()
+            .map(|(tag, target)| {
+                let density = *densities.get(tag.as_str()).unwrap_or(&0.0);
+                (density - target).abs() / target.max(1.0 - target)
+            })
+            .sum();
+        1.0 - distance / self.targets.len() as f64
+    }
+}
+
+pub const CARD_TYPES: [&str; 3] = ["Follower", "Spell", "Amulet"];
+
+// The share of the deck that each card type should make up, as (min, max) fractions in the order
+// of CARD_TYPES.
+pub type TypeRanges = [(f64, f64); 3];
+
+pub const DEFAULT_TYPE_RANGES: TypeRanges = [(0.55, 0.8), (0.15, 0.4), (0.0, 0.15)];
+
+// The archetypes whose decks are built differently from the default ranges.
+fn archetype_type_ranges(tag: &str) -> Option<TypeRanges> {
+    match tag {
+        "Spellboost" => Some([(0.3, 0.6), (0.4, 0.7), (0.0, 0.1)]),
+        "Amulet" => Some([(0.4, 0.7), (0.1, 0.3), (0.15, 0.4)]),
+        "Dirt" => Some([(0.4, 0.7), (0.1, 0.35), (0.1, 0.3)]),
+        "Artifact" | "Machina" => Some([(0.5, 0.8), (0.15, 0.4), (0.0, 0.2)]),
+        _ => None,
+    }
+}
+
+// How far the share of each card type is from its range.
+pub struct Types {
+    pub ranges: TypeRanges,
+}
+
+impl Types {
+    // The ranges of the chosen archetypes, merged so that a deck of any of them fits; the default
+    // ones if none of them has its own.
+    pub fn from_tags(tags: &[String]) -> Self {
+        let mut ranges: Option<TypeRanges> = None;
+        for archetype in tags.iter().filter_map(|t| archetype_type_ranges(t)) {
+            let mut merged = ranges.unwrap_or(archetype);
+            for (m, (lo, hi)) in merged.iter_mut().zip(archetype) {
+                *m = (m.0.min(lo), m.1.max(hi));
+            }
+            ranges = Some(merged);
+        }
+        Types { ranges: ranges.unwrap_or(DEFAULT_TYPE_RANGES) }
+    }
+
+    // The number of copies of each card type.
+    pub fn counts(ctx: &Context, deck: &DeckBTree) -> [i32; 3] {
+        let mut ret = [0; 3];
+        for (idx, qty) in &deck.0 {
+            let card = ctx.idx_to_card(*idx);
+            if let Some(i) = CARD_TYPES.iter().position(|t| *t == card.type_) {
+                ret[i] += *qty as i32;
+            }
+        }
+        ret
+    }
+
+    pub fn as_string(&self, ctx: &Context, deck: &DeckBTree) -> String {
+        let mut ret = String::from("Card types:\n");
+        let len = deck.len() as f64;
+        for ((name, count), (lo, hi)) in CARD_TYPES.iter().zip(Types::counts(ctx, deck)).zip(self.ranges) {
+            ret.push_str(&format!("\t{}: {} ({:.1}%), target {:.0}-{:.0}%\n",
+                                  name, count, 100.0 * count as f64 / len, 100.0 * lo, 100.0 * hi));
+        }
+        ret
+    }
+}
+
+impl FitnessComponent for Types {
+    fn name(&self) -> String {
+        String::from("types")
+    }
+
+    // 1 if every type is within its range, minus the total distance of the shares from the ranges.
+    fn score(&self, ctx: &Context, deck: &DeckBTree) -> f64 {
+        let len = deck.len() as f64;
+        let distance: f64 = Types::counts(ctx, deck).iter().zip(self.ranges)
+            .map(|(count, (lo, hi))| {
+                let share = *count as f64 / len;
+                (lo - share).max(share - hi).max(0.0)
+            })
+            .sum();
+        (1.0 - distance).max(0.0)
+    }
+}
+
+pub const ROLES: [&str; 7] = ["Removal", "AoE", "Draw", "Heal", "Ward", "Finisher", "Ramp"];
+
+// The fewest copies of each role a deck should run, by default.
+pub const DEFAULT_ROLE_MINIMUMS: [(&str, i32); 3] = [("Removal", 8), ("Draw", 4), ("AoE", 2)];
+
+// How well the deck covers the roles it needs: each role counts for the fraction of its minimum
+// number of copies that the deck runs.
+#[derive(Clone)]
+pub struct Roles {
+    pub minimums: Vec<(String, i32)>,
+}
+
+impl Default for Roles {
+    fn default() -> Self {
+        Roles { minimums: DEFAULT_ROLE_MINIMUMS.iter().map(|(r, n)| (r.to_string(), *n)).collect() }
+    }
+}
+
+impl Roles {
+    // Reads "role:n,role:n".
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut minimums = vec![];
+        for pair in s.split(',') {
+            let invalid = || format!("invalid role minimum: {}", pair);
+            let (role, n) = pair.split_once(':').ok_or_else(invalid)?;
+            let role = ROLES.iter().find(|r| r.eq_ignore_ascii_case(role.trim())).ok_or_else(invalid)?;
+            minimums.push((role.to_string(), n.trim().parse().map_err(|_| invalid())?));
+        }
+        Ok(Roles { minimums })
+    }
+
+    // The number of copies of each role in the deck, in the order of ROLES. A card can have
+    // several roles.
+    pub fn counts(ctx: &Context, deck: &DeckBTree) -> [i32; ROLES.len()] {
+        let mut ret = [0; ROLES.len()];
+        for (idx, qty) in &deck.0 {
+            for role in &ctx.idx_to_card(*idx).roles_ {
+                if let Some(i) = ROLES.iter().position(|r| r == role) {
+                    ret[i] += *qty as i32;
+                }
+            }
+        }
+        ret
+    }
+
+    pub fn as_string(&self, ctx: &Context, deck: &DeckBTree) -> String {
+        let mut ret = String::from("Roles:\n");
+        for (role, count) in ROLES.iter().zip(Roles::counts(ctx, deck)) {
+            ret.push_str(&format!("\t{}: {}", role, count));
+            if let Some((_, min)) = self.minimums.iter().find(|(r, _)| r == role) {
+                ret.push_str(&format!(" (at least {})", min));
+            }
+            ret.push('\n');
+        }
+        ret
+    }
+}
+
+impl FitnessComponent for Roles {
+    fn name(&self) -> String {
+        String::from("roles")
+    }
+
+    fn score(&self, ctx: &Context, deck: &DeckBTree) -> f64 {
+        if self.minimums.is_empty() { return 1.0; }
+        let counts = Roles::counts(ctx, deck);
+        let covered: f64 = self.minimums.iter()
+            .map(|(role, min)| {
+                let count = ROLES.iter().position(|r| r == role).map_or(0, |i| counts[i]);
+                if *min <= 0 { 1.0 } else { (count as f64 / *min as f64).min(1.0) }
+            })
+            .sum();
+        covered / self.minimums.len() as f64
+    }
+}
+
+// The fitness function, a weighed sum of components.
+// Clones share the components, which can be costly to build (like Synergy, learned from a corpus),
+// and only copy the weights.
+pub struct Fitness<D: Deck = DeckBTree>(pub Vec<(Arc<dyn FitnessComponent<D>>, f64)>);
+
+impl<D: Deck> Default for Fitness<D> {
+    fn default() -> Self {
+        Fitness(vec![])
+    }
+}
+
+impl<D: Deck> Clone for Fitness<D> {
+    fn clone(&self) -> Self {
+        Fitness(self.0.clone())
+    }
+}
+
+impl Fitness {
+    // The default components.
+    pub fn from_weights(w_curve: f64, w_tags: f64, w_length: f64) -> Self {
+        let mut ret = Fitness::default();
+        ret.add(Box::new(Curve::default()), w_curve);
+        ret.add(Box::new(Tags), w_tags);
+        ret.add(Box::new(Length), w_length);
+        ret
+    }
+
+    // The linear form of the weighed sum, if every component with a weight has one.
+    pub fn linear(&self, ctx: &Context) -> Option<LinearForm> {
+        let mut ret = LinearForm::zero(ctx);
+        for (component, weight) in self.0.iter().filter(|(_, w)| *w != 0.0) {
+            ret.add(&component.linear(ctx)?, *weight);
+        }
+        Some(ret)
+    }
+}
+
+impl<D: Deck> Fitness<D> {
+    pub fn add(&mut self, component: Box<dyn FitnessComponent<D>>, weight: f64) {
+        self.0.push((Arc::from(component), weight));
+    }
+
+    // Swaps the component with the same name for the given one, keeping its weight.
+    pub fn replace(&mut self, component: Box<dyn FitnessComponent<D>>) {
+        if let Some(entry) = self.0.iter_mut().find(|(c, _)| c.name() == component.name()) {
+            entry.0 = Arc::from(component);
+        }
+    }
+
+    // Changes the weight of the component with the given name. Returns false if there is none.
+    pub fn reweight(&mut self, name: &str, weight: f64) -> bool {
+        match self.0.iter_mut().find(|(c, _)| c.name() == name) {
+            Some(entry) => {
+                entry.1 = weight;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // The best possible score, since every component scores at most 1.
+    pub fn total_weight(&self) -> f64 {
+        self.0.iter().map(|(_, w)| w).sum()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.0.iter().map(|(c, _)| c.name()).collect()
+    }
+
+    // The unweighted score of each component.
+    pub fn scores(&self, ctx: &Context, deck: &D) -> Vec<f64> {
+        self.0.iter().map(|(c, _)| c.score(ctx, deck)).collect()
+    }
+
+    pub fn rate(&self, ctx: &Context, deck: &D) -> f64 {
+        self.0.iter()
+            .filter(|(_, w)| *w != 0.0)
+            .map(|(c, w)| c.score(ctx, deck) * w)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::Context;
+    use crate::deck::{Deck, DeckBTree, deck_of_cards};
+    use crate::fitness::{Curve, DEFAULT_TYPE_RANGES, Fitness, FitnessComponent, Roles, Types};
+
+    #[test]
+    fn curve() {
+        // the precomputed constants of the default curve.
+        let c = Curve::default();
+        assert!(f64::abs(c.ideal_module - 17.4928556845359) < 1e-9);
+        assert!(f64::abs(c.min_score - 0.1143323900950059) < 1e-9);
+        let ctx = Context::from_debug();
+        let d = DeckBTree::from_rand(&ctx);
+        let score = c.score(&ctx, &d);
+        assert!((0.0..=1.0).contains(&score));
+        let effective = Curve { effective: true, ..Curve::default() };
+        assert!((0.0..=1.0 + 1e-9).contains(&effective.score(&ctx, &d)));
+        // the effective curve of a deck without alternate costs is its plain curve.
+        let mut plain = DeckBTree::new();
+        let idx = (0..ctx.cards_vec.0.len()).find(|i| ctx.idx_to_card(*i).alt_costs_.is_empty()).unwrap();
+        plain.add(idx);
+        assert!(f64::abs(effective.score(&ctx, &plain) - c.score(&ctx, &plain)) < 1e-9);
+        let mut f = Fitness::from_weights(0.4, 0.4, 0.2);
+        f.replace(Box::new(effective));
+        assert_eq!(f.names(), vec!["curve", "tags", "length"]);
+        assert_eq!(f.0[0].1, 0.4);
+    }
+
+    #[test]
+    fn linear() {
+        let ctx = Context::from_debug();
+        let f = Fitness::from_weights(0.0, 0.7, 0.3);
+        let form = f.linear(&ctx).unwrap();
+        for _ in 0..10 {
+            let d = DeckBTree::from_rand(&ctx);
+            let mut score = form.constant + form.per_card * d.0.len() as f64;
+            for (idx, qty) in &d.0 {
+                score += form.per_copy[*idx] * *qty as f64;
+            }
+            assert!(f64::abs(score - f.rate(&ctx, &d)) < 1e-9);
+        }
+        // the curve score isn't linear.
+        assert!(Fitness::from_weights(0.1, 0.7, 0.2).linear(&ctx).is_none());
+    }
+
+    #[test]
+    fn weights() {
+        let ctx = Context::from_debug();
+        let d = DeckBTree::from_rand(&ctx);
+        let f = Fitness::from_weights(0.4, 0.4, 0.2);
+        assert_eq!(f.names(), vec!["curve", "tags", "length"]);
+        let s = f.scores(&ctx, &d);
+        assert!(f64::abs(f.rate(&ctx, &d) - (0.4 * s[0] + 0.4 * s[1] + 0.2 * s[2])) < 1e-9);
+        assert!(f64::abs(f.total_weight() - 1.0) < 1e-9);
+    }
+
+    #[test]
+    fn types() {
+        assert_eq!(Types::from_tags(&[]).ranges, DEFAULT_TYPE_RANGES);
+        assert_eq!(Types::from_tags(&[String::from("Buff")]).ranges, DEFAULT_TYPE_RANGES);
+        let spellboost = Types::from_tags(&[String::from("Spellboost")]).ranges;
+        assert_eq!(spellboost[1], (0.4, 0.7));
+        let both = Types::from_tags(&[String::from("Spellboost"), String::from("Amulet")]).ranges;
+        assert_eq!(both, [(0.3, 0.7), (0.1, 0.7), (0.0, 0.4)]);
+        let ctx = Context::from_debug();
+        let t = Types::from_tags(&[]);
+        // a deck of followers only: 20% over the follower range, 15% under the spell one.
+        let d = deck_of_cards(&ctx, |card| card.type_ == "Follower");
+        assert_eq!(Types::counts(&ctx, &d), [40, 0, 0]);
+        assert!(f64::abs(t.score(&ctx, &d) - 0.65) < 1e-9);
+        assert_eq!(t.as_string(&ctx, &d).lines().count(), 4);
+    }
+
+    #[test]
+    fn roles() {
+        let r = Roles::parse("removal:6, Draw:3").unwrap();
+        assert_eq!(r.minimums, vec![(String::from("Removal"), 6), (String::from("Draw"), 3)]);
+        assert!(Roles::parse("removal").is_err());
+        assert!(Roles::parse("lifelink:3").is_err());
+        let ctx = Context::from_debug();
+        let removal = (0..ctx.cards_vec.0.len())
+            .find(|i| ctx.idx_to_card(*i).roles_ == vec![String::from("Removal")])
+            .unwrap();
+        let mut d = DeckBTree::new();
+        for _ in 0..3 {
+            d.add(removal);
+        }
+        // half of the removal, none of the draw.
+        assert!(f64::abs(r.score(&ctx, &d) - 0.25) < 1e-9);
+        assert_eq!(Roles::counts(&ctx, &d)[0], 3);
+        assert!(r.as_string(&ctx, &d).contains("Removal: 3 (at least 6)"));
+        assert_eq!(Roles { minimums: vec![] }.score(&ctx, &d), 1.0);
+    }
+}
@@ -0,0 +1,175 @@
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+
+use crate::context::Context;
+use crate::deck::{Deck, DeckBTree};
+use crate::fitness::FitnessComponent;
+use crate::odds::{draws, OPENING_HAND};
+
+// The most pp a player can have.
+pub const MAX_PP: usize = 10;
+// The most cards a hand can hold: cards drawn beyond it are lost.
+pub const HAND_LIMIT: usize = 9;
+// Opening cards costing more than this are mulliganed.
+pub const MULLIGAN_PP: u8 = 3;
+
+// Plays games against an opponent that does nothing, to measure how often the deck can spend all
+// of its pp turn after turn.
+// Cards are only seen through their cost: effects like enhance or accelerate are ignored.
+// The games of a deck are shuffled from a seed taken from its key, so the same deck always gets the
+// same results.
+pub struct Goldfish {
+    pub games: usize,
+    pub turns: usize,
+}
+
+impl Goldfish {
+    // The positions in the hand of the cards that spend the most pp without going over it.
+    // A subset sum over the costs up to pp: each reachable cost keeps the card that first reached
+    // it, which was added to a cost reached by earlier cards only, so following the cards back
+    // from the highest cost gives the play.
+    fn best_play(hand: &[u8], pp: usize) -> Vec<usize> {
+        let mut last: Vec<Option<usize>> = vec![None; pp + 1];
+        for (i, cost) in hand.iter().map(|c| *c as usize).enumerate() {
+            if cost == 0 || cost > pp { continue; }
+            for c in (cost..=pp).rev() {
+                if last[c].is_none() && (c == cost || last[c - cost].is_some()) {
+                    last[c] = Some(i);
+                }
+            }
+        }
+        let mut ret = vec![];
+        let mut c = (1..=pp).rev().find(|c| last[*c].is_some()).unwrap_or(0);
+        while c > 0 {
+            let i = last[c].unwrap();
+            ret.push(i);
+            c -= hand[i] as usize;
+        }
+        ret.reverse();
+        ret
+    }
+
+    // Whether each turn of the game spent all the available pp.
+    fn play(&self, costs: &[u8], going_first: bool, rng: &mut StdRng) -> Vec<bool> {
+        let mut library = costs.to_vec();
+        library.shuffle(rng);
+        let mut hand: Vec<u8> = library.drain(..OPENING_HAND.min(library.len())).collect();
+        // the expensive cards are swapped for new ones, then shuffled back.
+        let (kept, swapped): (Vec<u8>, Vec<u8>) = hand.iter().partition(|c| **c <= MULLIGAN_PP);
+        hand = kept;
+        hand.extend(library.drain(..swapped.len().min(library.len())));
+        library.extend(swapped);
+        library.shuffle(rng);
+        let mut ret = vec![];
+        for turn in 1..=self.turns {
+            let n = if turn == 1 { draws(1, going_first) } else { 1 };
+            for _ in 0..n {
+                if let Some(card) = library.pop() {
+                    if hand.len() < HAND_LIMIT { hand.push(card); }
+                }
+            }
+            let pp = turn.min(MAX_PP);
+            let play = Goldfish::best_play(&hand, pp);
+            let spent: usize = play.iter().map(|i| hand[*i] as usize).sum();
+            ret.push(spent == pp);
+            for i in play.into_iter().rev() {
+                hand.remove(i);
+            }
+        }
+        ret
+    }
+
+    // The fraction of games that spent all the pp on each turn. Half of the games go first.
+    pub fn simulate(&self, ctx: &Context, deck: &DeckBTree) -> Vec<f64> {
+        let mut costs = vec![];
+        for (idx, qty) in &deck.0 {
+            for _ in 0..*qty {
+                costs.push(ctx.idx_to_card(*idx).pp_);
+            }
+        }
+        let mut ret = vec![0.0; self.turns];
+        let mut rng = StdRng::seed_from_u64(deck.key());
+        let first = rng.gen::<bool>();
+        for game in 0..self.games {
+            let going_first = first ^ game.is_multiple_of(2);
+            let game = self.play(&costs, going_first, &mut rng);
+            for (turn, curved_out) in game.into_iter().enumerate() {
+                if curved_out { ret[turn] += 1.0; }
+            }
+        }
+        ret.iter().map(|n| n / self.games as f64).collect()
+    }
+
+    pub fn as_string(&self, ctx: &Context, deck: &DeckBTree) -> String {
+        let mut ret = format!("Chance of spending all pp, over {} games:\n", self.games);
+        for (turn, p) in self.simulate(ctx, deck).iter().enumerate() {
+            ret.push_str(&format!("\tturn {}: {:.1}%\n", turn + 1, 100.0 * p));
+        }
+        ret
+    }
+}
+
+// The mean over the turns of the chance of spending all the pp.
+// NOTE: the score is an estimate, but a fixed one: it doesn't vary between evaluations of the same
+// deck, so it can be cached.
+impl FitnessComponent for Goldfish {
+    fn name(&self) -> String {
+        String::from("curve out")
+    }
+
+    fn score(&self, ctx: &Context, deck: &DeckBTree) -> f64 {
+        let odds = self.simulate(ctx, deck);
+        odds.iter().sum::<f64>() / odds.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use crate::context::Context;
+    use crate::deck::{Deck, DeckBTree};
+    use crate::fitness::FitnessComponent;
+    use crate::goldfish::{Goldfish, HAND_LIMIT};
+
+    #[test]
+    fn best_play() {
+        assert_eq!(Goldfish::best_play(&[2, 3, 5], 5), vec![0, 1]);
+        assert_eq!(Goldfish::best_play(&[4, 4, 1], 3), vec![2]);
+        assert!(Goldfish::best_play(&[], 3).is_empty());
+        assert!(Goldfish::best_play(&[0, 4], 3).is_empty());
+        // a full hand, where the most pp are only spent by skipping the cheapest cards.
+        let hand = [1, 1, 1, 6, 4, 2, 7, 3, 5];
+        assert_eq!(hand.len(), HAND_LIMIT);
+        let play = Goldfish::best_play(&hand, 10);
+        assert_eq!(play.iter().map(|i| hand[*i] as usize).sum::<usize>(), 10);
+        assert!(play.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(Goldfish::best_play(&[6, 7, 6], 9), vec![1]);
+    }
+
+    #[test]
+    fn games() {
+        let g = Goldfish { games: 10, turns: 10 };
+        let mut rng = StdRng::seed_from_u64(0);
+        // only 1pp cards: every turn is spent fully as long as the hand holds enough cards.
+        let ones = vec![1; 40];
+        let game = g.play(&ones, true, &mut rng);
+        assert_eq!(game.len(), 10);
+        assert!(game[0] && game[1]);
+        // only 9pp cards: the first turns are never spent fully.
+        let nines = vec![9; 40];
+        assert_eq!(g.play(&nines, false, &mut rng)[..8], [false; 8]);
+        let ctx = Context::from_debug();
+        let d = DeckBTree::from_rand(&ctx);
+        let odds = g.simulate(&ctx, &d);
+        assert_eq!(odds.len(), 10);
+        assert!(odds.iter().all(|p| (0.0..=1.0).contains(p)));
+        let score = g.score(&ctx, &d);
+        assert!((0.0..=1.0).contains(&score));
+        // the same deck always plays the same games.
+        assert_eq!(g.score(&ctx, &d), score);
+        assert_eq!(d.len(), 40);
+    }
+}
@@ -0,0 +1,116 @@
+use crate::context::Context;
+use crate::deck::Deck;
+
+// A bounded list of the best decks met during a run, sorted by decreasing score.
+// Decks closer than min_distance to each other compete for the same slot, so that clones of the
+// champion can't crowd out alternative builds.
+pub struct HallOfFame<D>
+    where D: Deck
+{
+    pub entries: Vec<(D, f64)>,
+    pub capacity: usize,
+    pub min_distance: i8,
+}
+
+impl<D: Clone + Deck> HallOfFame<D> {
+    pub fn new(capacity: usize, min_distance: i8) -> Self {
+        HallOfFame {
+            entries: vec![],
+            capacity,
+            min_distance,
+        }
+    }
+
+    // Whether a deck with the given score would be left out of a full hall.
+    fn rejects(&self, score: f64) -> bool {
+        self.entries.len() >= self.capacity
+            && self.entries.last().is_none_or(|(_, worst)| *worst >= score)
+    }
+
+    // Tries to add a deck to the hall. The deck is admitted if it beats every entry that is too
+    // similar to it, which are then evicted. Returns whether the deck was admitted.
+    pub fn offer(&mut self, deck: &D, score: f64) -> bool {
+        let mut neighbors = vec![];
+        for (i, (entry, entry_score)) in self.entries.iter().enumerate() {
+            if entry.distance(deck) < self.min_distance {
+                if *entry_score >= score { return false; }
+                neighbors.push(i);
+            }
+        }
+        if neighbors.is_empty() && self.rejects(score) { return false; }
+        for i in neighbors.into_iter().rev() {
+            self.entries.remove(i);
+        }
+        let pos = self.entries.iter().position(|(_, s)| *s < score).unwrap_or(self.entries.len());
+        self.entries.insert(pos, (deck.clone(), score));
+        self.entries.truncate(self.capacity);
+        true
+    }
+
+    // Offers the decks of a rated population to the hall.
+    // NOTE: assumes that the population is sorted by score.
+    pub fn update(&mut self, population: &[(D, f64)]) {
+        for (deck, score) in population {
+            if self.rejects(*score) { break; }
+            self.offer(deck, *score);
+        }
+    }
+
+    pub fn as_string(&self, ctx: &Context) -> String {
+        let mut ret = String::new();
+        for (i, (deck, score)) in self.entries.iter().enumerate() {
+            ret.push_str(&format!("\n#{} - fitness: {}", i + 1, score));
+            ret.push_str(&deck.as_string(ctx));
+            ret.push_str(&format!("{}\n", deck.url(ctx)));
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::Context;
+    use crate::deck::{Deck, DeckBTree};
+    use crate::hall_of_fame::HallOfFame;
+
+    #[test]
+    fn offer() {
+        let ctx = Context::from_debug();
+        let mut hof = HallOfFame::new(2, 5);
+        let d1 = DeckBTree::from_rand(&ctx);
+        let mut d2 = d1.clone();
+        d2.mutate(&ctx, 3, 3, 0.5, 1.0);
+        assert!(hof.offer(&d1, 0.5));
+        // too similar to a better deck
+        assert!(!hof.offer(&d2, 0.4));
+        // too similar to a worse deck, which gets replaced
+        assert!(hof.offer(&d2, 0.6));
+        assert_eq!(hof.entries.len(), 1);
+        assert_eq!(hof.entries[0].1, 0.6);
+    }
+
+    #[test]
+    fn capacity() {
+        let ctx = Context::from_debug();
+        let mut hof = HallOfFame::new(3, 0);
+        for i in 0..10 {
+            hof.offer(&DeckBTree::from_rand(&ctx), i as f64 / 10.0);
+        }
+        assert_eq!(hof.entries.len(), 3);
+        assert_eq!(hof.entries[0].1, 0.9);
+        assert_eq!(hof.entries[2].1, 0.7);
+        assert!(!hof.offer(&DeckBTree::from_rand(&ctx), 0.1));
+    }
+
+    #[test]
+    fn update() {
+        let ctx = Context::from_debug();
+        let mut hof = HallOfFame::new(10, 10);
+        let d = DeckBTree::from_rand(&ctx);
+        let population = vec![(d.clone(), 0.9), (d.clone(), 0.8), (d, 0.7)];
+        hof.update(&population);
+        // clones of the same deck only get one slot
+        assert_eq!(hof.entries.len(), 1);
+        assert_eq!(hof.entries[0].1, 0.9);
+    }
+}
@@ -0,0 +1,162 @@
+use std::thread;
+
+use crate::deck::Deck;
+use crate::hall_of_fame::HallOfFame;
+use crate::population::GA;
+
+// Which islands send their best decks to which.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Topology {
+    // Each island only sends decks to the next one.
+    Ring,
+    // Each island sends decks to all the others.
+    FullyConnected,
+}
+
+impl Topology {
+    // The indexes of the islands that the island i sends decks to, out of n islands.
+    fn destinations(&self, i: usize, n: usize) -> Vec<usize> {
+        match self {
+            Topology::Ring => if n > 1 { vec![(i + 1) % n] } else { vec![] },
+            Topology::FullyConnected => (0..n).filter(|j| *j != i).collect(),
+        }
+    }
+}
+
+// Several GAs evolving side by side, each on its own thread. Every few generations the best decks
+// of each island migrate to its neighbors, which mixes the exploration done by each population
+// without letting a single archetype take over all of them.
+pub struct Archipelago<D>
+    where D: Deck
+{
+    pub islands: Vec<GA<D>>,
+    // Islands that ran out of survivors stop evolving, but still send and receive decks.
+    pub alive: Vec<bool>,
+    pub topology: Topology,
+    pub migration_args: (usize, usize),
+}
+
+impl<D: Clone + Deck + PartialEq + Send> Archipelago<D> {
+    pub fn new(mut islands: Vec<GA<D>>, topology: Topology) -> Self {
+        for (i, island) in islands.iter_mut().enumerate() {
+            island.set_name(&format!("[ISLAND {}] ", i + 1));
+        }
+        Archipelago {
+            alive: vec![true; islands.len()],
+            islands,
+            topology,
+            migration_args: (10, 5),
+        }
+    }
+
+    // Every interval generations, the migrants best decks of each island are sent.
+    pub fn set_migration_args(&mut self, interval: usize, migrants: usize) {
+        self.migration_args = (interval, migrants);
+    }
+
+    fn migrate(&mut self) {
+        let (_, migrants) = self.migration_args;
+        let n = self.islands.len();
+        let mut incoming = vec![vec![]; n];
+        for (i, island) in self.islands.iter().enumerate() {
+            for j in self.topology.destinations(i, n) {
+                incoming[j].extend(island.emigrants(migrants));
+            }
+        }
+        for (island, decks) in self.islands.iter_mut().zip(incoming) {
+            island.immigrate(decks);
+        }
+    }
+
+    // Computes a unit of time for every island, and migrates decks when it's time to.
+    // Returns false once an island reaches its target fitness, or no island is left alive.
+    pub fn tick(&mut self) -> bool {
+        thread::scope(|s| {
+            for island in self.islands.iter_mut() {
+                s.spawn(move || island.update_params());
+            }
+        });
+        if self.islands.iter().any(|island| island.reached_target()) { return false; }
+        let (interval, _) = self.migration_args;
+        if interval > 0 && (self.islands[0].time as usize).is_multiple_of(interval) {
+            println!("\nMigrating...");
+            self.migrate();
+        }
+        thread::scope(|s| {
+            for (island, alive) in self.islands.iter_mut().zip(self.alive.iter_mut()) {
+                if *alive {
+                    s.spawn(move || *alive = island.reproduce());
+                }
+            }
+        });
+        self.alive.iter().any(|alive| *alive)
+    }
+
+    // The best distinct decks across all islands.
+    pub fn hall_of_fame(&self) -> HallOfFame<D> {
+        let first = &self.islands[0].hall_of_fame;
+        let mut ret = HallOfFame::new(first.capacity, first.min_distance);
+        for island in &self.islands {
+            for (deck, score) in &island.hall_of_fame.entries {
+                ret.offer(deck, *score);
+            }
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::Context;
+    use crate::deck::{Deck, DeckBTree};
+    use crate::islands::{Archipelago, Topology};
+    use crate::population::GA;
+
+    fn archipelago(n: usize, topology: Topology) -> Archipelago<DeckBTree> {
+        let ctx = Context::from_debug();
+        let mut islands = vec![];
+        for _ in 0..n {
+            let mut ga: GA<DeckBTree> = GA::from_rand(ctx.clone());
+            ga.set_rate_args(0.5, 0.0, 0.5);
+            ga.set_mutation_args(10, 3, 0.05);
+            ga.set_cull_args(0.0, 0.0, 0.0);
+            ga.set_hall_of_fame_args(3, 5);
+            islands.push(ga);
+        }
+        Archipelago::new(islands, topology)
+    }
+
+    #[test]
+    fn destinations() {
+        assert_eq!(Topology::Ring.destinations(2, 3), vec![0]);
+        assert_eq!(Topology::Ring.destinations(0, 1), Vec::<usize>::new());
+        assert_eq!(Topology::FullyConnected.destinations(1, 3), vec![0, 2]);
+    }
+
+    #[test]
+    fn migrate() {
+        let mut a = archipelago(2, Topology::Ring);
+        for island in a.islands.iter_mut() {
+            island.update_params();
+        }
+        let best = a.islands[0].population[0].clone();
+        a.set_migration_args(1, 1);
+        a.migrate();
+        // the best deck of the first island is now part of the second one.
+        assert!(a.islands[1].population.iter().any(|(d, _)| d.distance(&best.0) == 0));
+    }
+
+    #[test]
+    fn tick() {
+        let mut a = archipelago(3, Topology::FullyConnected);
+        a.set_migration_args(1, 2);
+        assert!(a.tick());
+        assert!(a.tick());
+        assert!(a.hall_of_fame().entries.len() <= 3);
+        assert!(!a.hall_of_fame().entries.is_empty());
+        for island in a.islands.iter_mut() {
+            island.set_stop_condition(0.0);
+        }
+        assert!(!a.tick());
+    }
+}
@@ -0,0 +1,339 @@
+use std::collections::VecDeque;
+
+use rand::Rng;
+
+use crate::context::Context;
+use crate::deck::{Deck, DeckBTree, MAX_QTY};
+use crate::fitness::Fitness;
+use crate::optimizer::Optimizer;
+use crate::population::POPULATION_SIZE;
+
+// A move of the local searches: a copy of the first card is replaced by a copy of the second.
+type Swap = (usize, usize);
+
+// Every swap that turns the deck into a different legal deck.
+fn swaps(ctx: &Context, deck: &DeckBTree) -> Vec<Swap> {
+    let mut ret = vec![];
+    for out in deck.0.keys() {
+        for card in 0..ctx.cards_vec.0.len() {
+            if card != *out && *deck.0.get(&card).unwrap_or(&0) < MAX_QTY {
+                ret.push((*out, card));
+            }
+        }
+    }
+    ret
+}
+
+fn apply(deck: &DeckBTree, (out, card): Swap) -> DeckBTree {
+    let mut ret = deck.clone();
+    ret.cut(out);
+    ret.add(card);
+    ret
+}
+
+// A random swap: a random copy is replaced by a random card of the cardpool.
+fn rand_swap(ctx: &Context, deck: &DeckBTree) -> Swap {
+    loop {
+        let out = deck.rand_idx();
+        let card = rand::thread_rng().gen_range(0, ctx.cards_vec.0.len());
+        if card != out && *deck.0.get(&card).unwrap_or(&0) < MAX_QTY {
+            return (out, card);
+        }
+    }
+}
+
+// Simulated annealing: random swaps are always accepted if they improve the deck, and with a
+// probability that decreases with the temperature and the score loss otherwise. The temperature
+// is multiplied by the cooling factor after every swap.
+// A tick is made of as many swaps as there are decks in a GA population.
+pub struct SimulatedAnnealing {
+    pub ctx: Context,
+    pub fitness: Fitness,
+    pub current: (DeckBTree, f64),
+    pub best: (DeckBTree, f64),
+    pub temperature: f64,
+    pub cooling: f64,
+    pub min_temperature: f64,
+    evaluations: u64,
+}
+
+impl SimulatedAnnealing {
+    pub fn from_rand(ctx: Context, fitness: Fitness, temperature: f64, cooling: f64,
+                     min_temperature: f64) -> Self {
+        let deck = DeckBTree::from_rand(&ctx);
+        let score = deck.rate(&ctx, &fitness);
+        SimulatedAnnealing {
+            ctx,
+            fitness,
+            current: (deck.clone(), score),
+            best: (deck, score),
+            temperature,
+            cooling,
+            min_temperature,
+            evaluations: 1,
+        }
+    }
+}
+
+impl Optimizer<DeckBTree> for SimulatedAnnealing {
+    fn name(&self) -> String {
+        String::from("sa")
+    }
+
+    fn ctx(&self) -> &Context {
+        &self.ctx
+    }
+
+    fn fitness(&self) -> &Fitness {
+        &self.fitness
+    }
+
+    fn tick(&mut self) -> bool {
+        for _ in 0..POPULATION_SIZE {
+            let neighbor = apply(&self.current.0, rand_swap(&self.ctx, &self.current.0));
+            let score = neighbor.rate(&self.ctx, &self.fitness);
+            self.evaluations += 1;
+            let delta = score - self.current.1;
+            if delta >= 0.0 || rand::thread_rng().gen::<f64>() < (delta / self.temperature).exp() {
+                self.current = (neighbor, score);
+                if score > self.best.1 {
+                    self.best = self.current.clone();
+                }
+            }
+            self.temperature *= self.cooling;
+        }
+        self.temperature > self.min_temperature
+    }
+
+    fn best(&self) -> (DeckBTree, f64) {
+        self.best.clone()
+    }
+
+    fn evaluations(&self) -> u64 {
+        self.evaluations
+    }
+}
+
+// The best swap of the deck, if it improves its score, along with the resulting deck.
+fn best_neighbor<F>(ctx: &Context, fitness: &Fitness, deck: &DeckBTree, mut allowed: F,
+                    evaluations: &mut u64) -> Option<(Swap, DeckBTree, f64)>
+    where F: FnMut(Swap, f64) -> bool {
+    let mut ret: Option<(Swap, DeckBTree, f64)> = None;
+    for swap in swaps(ctx, deck) {
+        let neighbor = apply(deck, swap);
+        let score = neighbor.rate(ctx, fitness);
+        *evaluations += 1;
+        if allowed(swap, score) && ret.as_ref().is_none_or(|(_, _, best)| score > *best) {
+            ret = Some((swap, neighbor, score));
+        }
+    }
+    ret
+}
+
+// Steepest-ascent hill climbing: at every tick, the best swap is applied. Once no swap improves
+// the deck, it is a local optimum, and the search restarts from a random deck.
+pub struct HillClimbing {
+    pub ctx: Context,
+    pub fitness: Fitness,
+    pub current: (DeckBTree, f64),
+    pub best: (DeckBTree, f64),
+    pub restarts: usize,
+    evaluations: u64,
+}
+
+impl HillClimbing {
+    pub fn from_rand(ctx: Context, fitness: Fitness, restarts: usize) -> Self {
+        let deck = DeckBTree::from_rand(&ctx);
+        let score = deck.rate(&ctx, &fitness);
+        HillClimbing {
+            ctx,
+            fitness,
+            current: (deck.clone(), score),
+            best: (deck, score),
+            restarts,
+            evaluations: 1,
+        }
+    }
+}
+
+impl Optimizer<DeckBTree> for HillClimbing {
+    fn name(&self) -> String {
+        String::from("hill")
+    }
+
+    fn ctx(&self) -> &Context {
+        &self.ctx
+    }
+
+    fn fitness(&self) -> &Fitness {
+        &self.fitness
+    }
+
+    fn tick(&mut self) -> bool {
+        let current = self.current.1;
+        match best_neighbor(&self.ctx, &self.fitness, &self.current.0, |_, score| score > current,
+                            &mut self.evaluations) {
+            Some((_, deck, score)) => {
+                self.current = (deck, score);
+                if score > self.best.1 {
+                    self.best = self.current.clone();
+                }
+                true
+            }
+            None => {
+                if self.restarts == 0 { return false; }
+                self.restarts -= 1;
+                let deck = DeckBTree::from_rand(&self.ctx);
+                let score = deck.rate(&self.ctx, &self.fitness);
+                self.evaluations += 1;
+                self.current = (deck, score);
+                true
+            }
+        }
+    }
+
+    fn best(&self) -> (DeckBTree, f64) {
+        self.best.clone()
+    }
+
+    fn evaluations(&self) -> u64 {
+        self.evaluations
+    }
+}
+
+// Tabu search: at every tick the best swap is applied, even if it worsens the deck, which lets
+// the search walk out of local optima. To avoid going back and forth, the cards involved in the
+// last tenure swaps can't be swapped back, unless doing so finds a new best deck.
+pub struct Tabu {
+    pub ctx: Context,
+    pub fitness: Fitness,
+    pub current: (DeckBTree, f64),
+    pub best: (DeckBTree, f64),
+    pub tenure: usize,
+    pub iterations: usize,
+    // The recent swaps, most recent last.
+    tabu: VecDeque<Swap>,
+    evaluations: u64,
+}
+
+impl Tabu {
+    pub fn from_rand(ctx: Context, fitness: Fitness, tenure: usize, iterations: usize) -> Self {
+        let deck = DeckBTree::from_rand(&ctx);
+        let score = deck.rate(&ctx, &fitness);
+        Tabu {
+            ctx,
+            fitness,
+            current: (deck.clone(), score),
+            best: (deck, score),
+            tenure,
+            iterations,
+            tabu: VecDeque::new(),
+            evaluations: 1,
+        }
+    }
+}
+
+impl Optimizer<DeckBTree> for Tabu {
+    fn name(&self) -> String {
+        String::from("tabu")
+    }
+
+    fn ctx(&self) -> &Context {
+        &self.ctx
+    }
+
+    fn fitness(&self) -> &Fitness {
+        &self.fitness
+    }
+
+    fn tick(&mut self) -> bool {
+        if self.iterations == 0 { return false; }
+        self.iterations -= 1;
+        let (tabu, best) = (&self.tabu, self.best.1);
+        // a swap is tabu if it puts back a card recently cut, or cuts a card recently added.
+        let allowed = |(out, card): Swap, score: f64| {
+            score > best || tabu.iter().all(|(t_out, t_card)| card != *t_out && out != *t_card)
+        };
+        match best_neighbor(&self.ctx, &self.fitness, &self.current.0, allowed,
+                            &mut self.evaluations) {
+            Some((swap, deck, score)) => {
+                self.current = (deck, score);
+                if score > self.best.1 {
+                    self.best = self.current.clone();
+                }
+                self.tabu.push_back(swap);
+                if self.tabu.len() > self.tenure {
+                    self.tabu.pop_front();
+                }
+                self.iterations > 0
+            }
+            None => false,
+        }
+    }
+
+    fn best(&self) -> (DeckBTree, f64) {
+        self.best.clone()
+    }
+
+    fn evaluations(&self) -> u64 {
+        self.evaluations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::Context;
+    use crate::deck::{Deck, DECK_SIZE, DeckBTree};
+    use crate::fitness::Fitness;
+    use crate::local_search::{apply, HillClimbing, SimulatedAnnealing, swaps, Tabu};
+    use crate::optimizer::Optimizer;
+
+    #[test]
+    fn swap() {
+        let ctx = Context::from_debug();
+        let d = DeckBTree::from_rand(&ctx);
+        let swaps = swaps(&ctx, &d);
+        assert!(!swaps.is_empty());
+        for swap in swaps.iter().take(100) {
+            let neighbor = apply(&d, *swap);
+            assert_eq!(neighbor.len(), DECK_SIZE);
+            assert_eq!(neighbor.distance(&d), 1);
+        }
+    }
+
+    #[test]
+    fn simulated_annealing() {
+        let ctx = Context::from_debug();
+        let mut sa = SimulatedAnnealing::from_rand(ctx, Fitness::from_weights(0.5, 0.0, 0.5),
+                                                   0.1, 0.99, 0.01);
+        let start = sa.best.1;
+        assert!(!sa.tick());
+        assert!(sa.best().1 >= start);
+        assert!(sa.best().1 >= sa.current.1);
+        assert_eq!(sa.best().0.len(), DECK_SIZE);
+    }
+
+    #[test]
+    fn hill_climbing() {
+        let ctx = Context::from_debug();
+        let mut hc = HillClimbing::from_rand(ctx, Fitness::from_weights(0.5, 0.0, 0.5), 0);
+        let mut last = hc.current.1;
+        for _ in 0..3 {
+            hc.tick();
+            // without restarts the score never decreases.
+            assert!(hc.current.1 >= last);
+            last = hc.current.1;
+        }
+    }
+
+    #[test]
+    fn tabu() {
+        let ctx = Context::from_debug();
+        let mut t = Tabu::from_rand(ctx, Fitness::from_weights(0.5, 0.0, 0.5), 2, 3);
+        assert!(t.tick());
+        assert!(t.tick());
+        assert!(!t.tick());
+        assert_eq!(t.tabu.len(), 2);
+        assert!(t.best().1 >= t.current.1);
+    }
+}
@@ -0,0 +1,255 @@
+use fnv::{FnvHashMap, FnvHashSet};
+use rand::seq::SliceRandom;
+
+use crate::context::{CardsMap, Context};
+use crate::goldfish::MAX_PP;
+use crate::synergy::Synergy;
+
+// Decides which locus of the genome each card takes. mutate swaps cards within a window of loci,
+// and mix crosses decks over at a single locus, so orderings that put cards that work together
+// near each other help the GA keep and combine card packages (low-order schemata).
+pub trait LocusOrdering {
+    fn name(&self) -> String;
+    // Orders the ids of the cards, first locus first.
+    fn order(&self, data: &CardsMap, ids: &mut [i32]);
+}
+
+// A chain of stable sorts: by pp, then tags, then type, craft, trait and id.
+pub struct Attributes;
+
+impl LocusOrdering for Attributes {
+    fn name(&self) -> String {
+        String::from("attributes")
+    }
+
+    fn order(&self, data: &CardsMap, ids: &mut [i32]) {
+        ids.sort_unstable_by_key(|c| data.0.get(c).unwrap().id_);
+        ids.sort_by_key(|c| data.0.get(c).unwrap().trait_.to_string());
+        ids.sort_by_key(|c| data.0.get(c).unwrap().craft_.to_string());
+        ids.sort_by_key(|c| data.0.get(c).unwrap().type_.to_string());
+        ids.sort_by_key(|c| &data.0.get(c).unwrap().tags_);
+        ids.sort_by_key(|c| data.0.get(c).unwrap().pp_);
+    }
+}
+
+// A random ordering, as a baseline for the others.
+pub struct Shuffled;
+
+impl LocusOrdering for Shuffled {
+    fn name(&self) -> String {
+        String::from("shuffled")
+    }
+
+    fn order(&self, _data: &CardsMap, ids: &mut [i32]) {
+        ids.shuffle(&mut rand::thread_rng());
+    }
+}
+
+// What makes two cards similar.
+pub enum Feature {
+    Tags,
+    Roles,
+    // The TF-IDF of the words of the effect texts.
+    Text,
+    // The normalized pmi of the cards in a corpus of decks (see synergy.rs), by pair of card ids.
+    Cooccurrence(FnvHashMap<(i32, i32), f64>),
+}
+
+// How much closeness in pp adds to the similarity of two cards. It breaks the ties between cards
+// with nothing in common, and keeps the curve somewhat in order.
+pub const PP_WEIGHT: f64 = 0.1;
+// The most passes of 2-opt over the seriation.
+pub const MAX_PASSES: usize = 20;
+
+// Orders the cards so that the total similarity of neighbouring cards is high: a 1-D seriation,
+// built by chaining each card to the most similar one left, then improved with 2-opt (reversing
+// the stretches of the chain whose ends would be more similar to their new neighbours).
+pub struct Seriation {
+    pub feature: Feature,
+}
+
+impl Seriation {
+    // The corpus co-occurrence of the cards of the context, by id.
+    pub fn cooccurrence(ctx: &Context, synergy: &Synergy) -> Feature {
+        let id = |idx: usize| ctx.cards_vec.0[idx];
+        let mut ret = FnvHashMap::default();
+        for (a, b) in synergy.pairs.keys() {
+            let npmi = synergy.npmi(*a, *b).unwrap();
+            ret.insert((id(*a), id(*b)), npmi);
+            ret.insert((id(*b), id(*a)), npmi);
+        }
+        Feature::Cooccurrence(ret)
+    }
+
+    // The words of the effect text of each card weighted by TF-IDF.
+    fn tf_idf(data: &CardsMap, ids: &[i32]) -> Vec<FnvHashMap<String, f64>> {
+        let words: Vec<Vec<String>> = ids.iter()
+            .map(|id| data.0.get(id).unwrap().effect_
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|w| w.len() > 2)
+                .map(|w| w.to_lowercase())
+                .collect())
+            .collect();
+        let mut df: FnvHashMap<&str, f64> = FnvHashMap::default();
+        for card in &words {
+            for word in card.iter().collect::<FnvHashSet<_>>() {
+                *df.entry(word).or_insert(0.0) += 1.0;
+            }
+        }
+        let n = ids.len() as f64;
+        words.iter()
+            .map(|card| {
+                let mut ret = FnvHashMap::default();
+                for word in card {
+                    *ret.entry(word.clone()).or_insert(0.0) += (n / df[word.as_str()]).ln();
+                }
+                ret
+            })
+            .collect()
+    }
+
+    fn cosine(a: &FnvHashMap<String, f64>, b: &FnvHashMap<String, f64>) -> f64 {
+        let dot: f64 = a.iter().filter_map(|(k, x)| b.get(k).map(|y| x * y)).sum();
+        let norm = |v: &FnvHashMap<String, f64>| v.values().map(|x| x * x).sum::<f64>().sqrt();
+        let norms = norm(a) * norm(b);
+        if norms == 0.0 { 0.0 } else { dot / norms }
+    }
+
+    // The similarity of every pair of the cards, in the order of ids.
+    pub fn similarities(&self, data: &CardsMap, ids: &[i32]) -> Vec<Vec<f64>> {
+        let card = |i: usize| data.0.get(&ids[i]).unwrap();
+        let as_set = |v: &Vec<String>| v.iter().map(|s| (s.clone(), 1.0)).collect();
+        let vectors: Vec<FnvHashMap<String, f64>> = match &self.feature {
+            Feature::Tags => (0..ids.len()).map(|i| as_set(&card(i).tags_)).collect(),
+            Feature::Roles => (0..ids.len()).map(|i| as_set(&card(i).roles_)).collect(),
+            Feature::Text => Seriation::tf_idf(data, ids),
+            Feature::Cooccurrence(_) => vec![],
+        };
+        let mut ret = vec![vec![0.0; ids.len()]; ids.len()];
+        for a in 0..ids.len() {
+            for b in a + 1..ids.len() {
+                let similarity = match &self.feature {
+                    Feature::Cooccurrence(npmi) => {
+                        npmi.get(&(ids[a], ids[b])).map_or(0.0, |x| (x + 1.0) / 2.0)
+                    }
+                    _ => Seriation::cosine(&vectors[a], &vectors[b]),
+                };
+                let pp = (card(a).pp_ as f64 - card(b).pp_ as f64).abs().min(MAX_PP as f64);
+                ret[a][b] = similarity + PP_WEIGHT * (1.0 - pp / MAX_PP as f64);
+                ret[b][a] = ret[a][b];
+            }
+        }
+        ret
+    }
+
+    // The positions in ids of the cards, in seriation order.
+    fn path(similarities: &[Vec<f64>]) -> Vec<usize> {
+        let n = similarities.len();
+        if n == 0 { return vec![]; }
+        let mut path = vec![0];
+        let mut left: Vec<usize> = (1..n).collect();
+        while !left.is_empty() {
+            let last = *path.last().unwrap();
+            let mut next = 0;
+            for (i, card) in left.iter().enumerate() {
+                if similarities[last][*card] > similarities[last][left[next]] { next = i; }
+            }
+            path.push(left.remove(next));
+        }
+        // reversing path[i + 1..=j] swaps the edges (i, i + 1) and (j, j + 1) for (i, j) and
+        // (i + 1, j + 1). The end of the path has no edge after it.
+        let edge = |path: &[usize], a: usize, b: usize| {
+            if b < n { similarities[path[a]][path[b]] } else { 0.0 }
+        };
+        for _ in 0..MAX_PASSES {
+            let mut improved = false;
+            for i in 0..n.saturating_sub(2) {
+                for j in i + 2..n {
+                    let gain = edge(&path, i, j) + edge(&path, i + 1, j + 1)
+                        - edge(&path, i, i + 1) - edge(&path, j, j + 1);
+                    if gain > 1e-9 {
+                        path[i + 1..=j].reverse();
+                        improved = true;
+                    }
+                }
+            }
+            if !improved { break; }
+        }
+        path
+    }
+}
+
+impl LocusOrdering for Seriation {
+    fn name(&self) -> String {
+        String::from(match self.feature {
+            Feature::Tags => "tags",
+            Feature::Roles => "roles",
+            Feature::Text => "text",
+            Feature::Cooccurrence(_) => "cooccurrence",
+        })
+    }
+
+    // The chain starts from the first card of the attributes ordering, and ties are broken in
+    // that order too.
+    fn order(&self, data: &CardsMap, ids: &mut [i32]) {
+        Attributes.order(data, ids);
+        let path = Seriation::path(&self.similarities(data, ids));
+        let ordered: Vec<i32> = path.into_iter().map(|i| ids[i]).collect();
+        ids.copy_from_slice(&ordered);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::{CardsVec, Context};
+    use crate::deck::{Deck, DeckBTree};
+    use crate::locus::{Attributes, Feature, LocusOrdering, Seriation, Shuffled};
+    use crate::synergy::Synergy;
+
+    // The total similarity of neighbouring cards along the path.
+    fn path_similarity(similarities: &[Vec<f64>], path: &[usize]) -> f64 {
+        path.windows(2).map(|w| similarities[w[0]][w[1]]).sum()
+    }
+
+    #[test]
+    fn orderings() {
+        let ctx = Context::from_debug();
+        let mut ids = ctx.cards_vec.0.clone();
+        Shuffled.order(&ctx.cards_map, &mut ids);
+        Attributes.order(&ctx.cards_map, &mut ids);
+        assert_eq!(ids, CardsVec::from_dict(&ctx.cards_map).0);
+        let sorted = ids.clone();
+        for feature in [Feature::Tags, Feature::Roles, Feature::Text] {
+            let s = Seriation { feature };
+            let mut seriated = sorted.clone();
+            s.order(&ctx.cards_map, &mut seriated);
+            let mut cards = seriated.clone();
+            cards.sort();
+            let mut all = sorted.clone();
+            all.sort();
+            assert_eq!(cards, all);
+            // the seriation puts similar cards closer than the attributes ordering does.
+            let similarities = s.similarities(&ctx.cards_map, &sorted);
+            let path: Vec<usize> = seriated.iter()
+                .map(|id| sorted.iter().position(|c| c == id).unwrap())
+                .collect();
+            let identity: Vec<usize> = (0..sorted.len()).collect();
+            assert!(path_similarity(&similarities, &path) > path_similarity(&similarities, &identity));
+        }
+    }
+
+    #[test]
+    fn reorder() {
+        let mut ctx = Context::from_debug();
+        let d = DeckBTree::from_rand(&ctx);
+        let synergy = Synergy::from_decks(&[d]);
+        let s = Seriation { feature: Seriation::cooccurrence(&ctx, &synergy) };
+        let first = ctx.idx_to_card(0).id_;
+        ctx.reorder(&Shuffled);
+        ctx.reorder(&s);
+        assert_eq!(ctx.idx_to_card(0).id_, first);
+        for idx in 0..ctx.cards_vec.0.len() {
+            assert_eq!(ctx.id_to_idx(ctx.idx_to_card(idx).id_), Some(idx));
+        }
+    }
+}
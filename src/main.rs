@@ -1,21 +1,570 @@
+use std::collections::HashMap;
+use std::env;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::archetype::{clusters, label_as_string};
 use crate::context::Context;
-use crate::deck::{Deck, DeckBTree};
+use crate::deck::{Deck, DeckBTree, hist};
+use crate::decklist::{from_url, read_decks, to_list};
+use crate::diff::diff;
+use crate::distance::summary;
+use crate::exact::solve;
+use crate::export::export;
+use crate::fitness::{Curve, Fitness, Roles, Types};
+use crate::goldfish::Goldfish;
+use crate::hall_of_fame::HallOfFame;
+use crate::islands::{Archipelago, Topology};
+use crate::local_search::{HillClimbing, SimulatedAnnealing, Tabu};
+use crate::locus::{Attributes, Feature, LocusOrdering, Seriation, Shuffled};
+use crate::odds::{Consistency, Group, odds_as_string};
+use crate::optimizer::{Optimizer, run};
+use crate::pareto::NSGA2;
 use crate::population::GA;
+use crate::schedule::Adaptive;
+use crate::synergy::{read_corpus, Synergy};
+use crate::targets::{choose, learn, load, save, Targets};
+use crate::tuning::{checkpoints, evaluate, Preset, rank, reordered, Space, trials_as_string};
+use crate::upgrade::report;
 
+mod archetype;
+mod cache;
 mod card;
 mod context;
 mod deck;
+mod decklist;
+mod diff;
+mod distance;
+mod exact;
+mod export;
+mod fitness;
+mod goldfish;
+mod hall_of_fame;
+mod islands;
+mod local_search;
+mod locus;
+mod odds;
+mod optimizer;
+mod pareto;
 mod population;
+mod schedule;
+mod synergy;
+mod targets;
+mod tuning;
+mod upgrade;
 
-fn main() {
-    let mut g = GA::<DeckBTree>::from_rand(Context::from_input());
-    g.set_rate_args(0.4, 0.4, 0.2);
-    g.set_mutation_args(20, 3, 0.05);
-    g.set_cull_args(0.3, 1.0, 0.005);
-    g.set_stop_condition(1.0);
+// The command line arguments, split in positional ones and "--name value" options.
+struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+    // The role minimums given with --role-minimums, or the default ones.
+    roles: Roles,
+    // The targets chosen from --targets, once the craft is known (see targets).
+    targets: Option<Targets>,
+}
+
+impl Args {
+    fn from_env() -> Self {
+        let mut positional = vec![];
+        let mut options = HashMap::new();
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.strip_prefix("--") {
+                Some(name) => { options.insert(name.to_string(), args.next().unwrap_or_default()); }
+                None => positional.push(arg),
+            }
+        }
+        let roles = match options.get("role-minimums") {
+            Some(minimums) => Roles::parse(minimums).unwrap_or_else(|e| panic!("{}", e)),
+            None => Roles::default(),
+        };
+        Args { positional, options, roles, targets: None }
+    }
+
+    fn mode(&self) -> Option<&str> {
+        self.positional.first().map(|s| s.as_str())
+    }
+
+    // The ith positional argument after the mode.
+    fn get<T: FromStr>(&self, i: usize, default: T) -> T {
+        self.positional.get(i + 1).and_then(|s| s.parse().ok()).unwrap_or(default)
+    }
+
+    fn option<T: FromStr>(&self, name: &str, default: T) -> T {
+        self.options.get(name).and_then(|s| s.parse().ok()).unwrap_or(default)
+    }
+}
+
+// The decks that the initial population is seeded with, if any.
+fn seeds(ctx: &Context, args: &Args) -> Vec<DeckBTree> {
+    match args.options.get("seed") {
+        Some(path) => read_decks(ctx, path).unwrap().0,
+        None => vec![],
+    }
+}
+
+const RATE_ARGS: (f64, f64, f64) = (0.4, 0.4, 0.2);
+// The games played to rate each deck with --curve-out: few, since every deck of every generation
+// is rated.
+const CURVE_OUT_GAMES: usize = 100;
+
+// Writes the deck to the file given with --export, if any.
+fn export_deck(ctx: &Context, fitness: &Fitness, deck: &DeckBTree, args: &Args) {
+    if let Some(path) = args.options.get("export") {
+        export(ctx, fitness, deck, path).unwrap();
+        println!("Deck exported to {}", path);
+    }
+}
+
+// Reads "--consistency-args k,turn,weight": at least k cards of the group by the turn, counting
+// for weight in the fitness.
+fn consistency_args(args: &Args) -> (usize, usize, f64) {
+    let values: Vec<String> = args.option("consistency-args", String::from("1,3,0.2"))
+        .split(',').map(String::from).collect();
+    let value = |i: usize| values.get(i).map(|s| s.trim().to_string()).unwrap_or_default();
+    (value(0).parse().unwrap_or(1), value(1).parse().unwrap_or(3), value(2).parse().unwrap_or(0.2))
+}
+
+// The archetype, effective curve, card types and roles of the deck.
+fn composition(ctx: &Context, deck: &DeckBTree, args: &Args) -> String {
+    let archetype = format!("Archetype: {}\n", label_as_string(ctx, deck));
+    let curve = deck.effective_pp_curve(ctx).map(|qty| qty.round() as i32);
+    archetype + &format!("Effective curve (alternate costs included):{}", hist(&curve, "#", 1))
+        + &Types::from_tags(&ctx.tags).as_string(ctx, deck)
+        + &args.roles.as_string(ctx, deck)
+}
+
+// The GA settings given with --preset, or the default ones.
+fn preset(args: &Args) -> Preset {
+    match args.options.get("preset") {
+        Some(path) => Preset::load(path).unwrap(),
+        None => Preset { rate_args: RATE_ARGS, mutation_args: (20, 3, 0.05), cull_args: (0.3, 1.0, 0.005) },
+    }
+}
+
+// The locus ordering with the given name. The cooccurrence ordering learns from the decks of
+// --corpus.
+fn ordering(ctx: &Context, name: &str, args: &Args) -> Box<dyn LocusOrdering> {
+    match name {
+        "attributes" => Box::new(Attributes),
+        "shuffled" => Box::new(Shuffled),
+        "tags" => Box::new(Seriation { feature: Feature::Tags }),
+        "roles" => Box::new(Seriation { feature: Feature::Roles }),
+        "text" => Box::new(Seriation { feature: Feature::Text }),
+        "cooccurrence" => {
+            let corpus = args.options.get("corpus").expect("missing --corpus for the cooccurrence ordering");
+            let synergy = Synergy::from_decks(&read_corpus(ctx, corpus).unwrap());
+            Box::new(Seriation { feature: Seriation::cooccurrence(ctx, &synergy) })
+        }
+        _ => panic!("unknown locus ordering: {}", name),
+    }
+}
+
+// The targets chosen from --targets for the craft and --archetype.
+fn targets(ctx: &Context, args: &Args) -> Option<Targets> {
+    args.options.get("targets").map(|path| {
+        let targets = load(path).unwrap();
+        choose(ctx, &targets, args.options.get("archetype").map(|a| a.as_str())).unwrap().clone()
+    })
+}
+
+// The fitness that decks are rated with in every mode: the curve, tags and length scores weighted
+// by the preset, the card types score, plus the optional components.
+fn fitness(ctx: &Context, args: &Args) -> Fitness {
+    let (w_curve, w_tags, w_length) = preset(args).rate_args;
+    let mut fitness = Fitness::from_weights(w_curve, w_tags, w_length);
+    let targets = &args.targets;
+    let mut curve = targets.as_ref().map_or_else(Curve::default, |t| Curve::new(t.ideal_curve()));
+    curve.effective = match args.option("curve", String::from("plain")).as_str() {
+        "plain" => false,
+        "effective" => true,
+        other => panic!("unknown curve: {} (plain or effective)", other),
+    };
+    fitness.replace(Box::new(curve));
+    let w_types = args.option("types", 0.1);
+    if w_types > 0.0 {
+        let types = targets.as_ref().map_or_else(|| Types::from_tags(&ctx.tags), |t| t.types());
+        fitness.add(Box::new(types), w_types);
+    }
+    if let Some(targets) = targets {
+        fitness.add(Box::new(targets.tag_densities()), args.option("tag-densities", 0.2));
+    }
+    if let Some(weight) = args.options.get("roles").and_then(|w| w.parse().ok()) {
+        fitness.add(Box::new(args.roles.clone()), weight);
+    }
+    if let Some(group) = args.options.get("consistency") {
+        let group = Group::parse(ctx, group).unwrap();
+        let (k, turn, weight) = consistency_args(args);
+        fitness.add(Box::new(Consistency { group, k, turn }), weight);
+    }
+    if let Some(weight) = args.options.get("curve-out").and_then(|w| w.parse().ok()) {
+        fitness.add(Box::new(Goldfish { games: CURVE_OUT_GAMES, turns: 10 }), weight);
+    }
+    if let Some(path) = args.options.get("synergy") {
+        let synergy = Synergy::from_decks(&read_corpus(ctx, path).unwrap());
+        fitness.add(Box::new(synergy), args.option("synergy-weight", 0.2));
+    }
+    fitness
+}
+
+fn configure(g: &mut GA<DeckBTree>, args: &Args) {
+    let fitness = fitness(&g.ctx, args);
+    configure_with(g, args, fitness);
+}
+
+// Sets up the GA from the arguments, to optimize the given fitness.
+fn configure_with(g: &mut GA<DeckBTree>, args: &Args, fitness: Fitness) {
+    preset(args).apply(g);
+    g.set_stop_condition(fitness.total_weight());
+    g.set_fitness(fitness);
+    g.set_hall_of_fame_args(10, 8);
+    if g.ctx.tags.len() > 1 {
+        g.set_niching_args(20, 1.0);
+    }
+    g.set_replacement_args(args.option("elites", 1), args.option("immigrants", 0.0));
+    if args.option("schedule", String::new()) == "adaptive" {
+        g.set_schedule(Box::new(Adaptive::new(10, 0.1, 0.5)));
+    }
+    let seeds = seeds(&g.ctx, args);
+    g.seed(&seeds, args.option("seed-fraction", 0.25));
+}
+
+fn run_ga(ctx: Context, args: &Args) {
+    let mut g = GA::<DeckBTree>::from_rand(ctx);
+    configure(&mut g, args);
     while g.tick() {}
-    println!("{}", g.population[0].0.as_string(&g.ctx));
-    println!("{}", g.population[0].0.url(&g.ctx));
+    println!("\nHALL OF FAME");
+    println!("{}", g.hall_of_fame.as_string(&g.ctx));
+    println!("{}", hall_of_fame_distances(&g.ctx, &g.hall_of_fame));
+    if let Some((deck, _)) = g.hall_of_fame.entries.first() {
+        println!("\nBEST DECK\n{}", composition(&g.ctx, deck, args));
+        export_deck(&g.ctx, &g.fitness, deck, args);
+    }
+}
+
+// How different the decks of the hall of fame are from each other, and which archetypes they
+// belong to.
+fn hall_of_fame_distances(ctx: &Context, hall_of_fame: &HallOfFame<DeckBTree>) -> String {
+    let decks: Vec<DeckBTree> = hall_of_fame.entries.iter().map(|(d, _)| d.clone()).collect();
+    let mut ret = summary(ctx, &decks) + "\nArchetypes:";
+    for (label, members) in clusters(ctx, &decks) {
+        let members: Vec<String> = members.iter().map(|i| format!("#{}", i + 1)).collect();
+        ret.push_str(&format!("\n\t{}: {}", label, members.join(", ")));
+    }
+    ret
+}
+
+// Each island gets a different starting mutation temperature, to vary how far they explore.
+fn run_islands(ctx: Context, args: &Args) {
+    let n = args.get(0, 4);
+    let topology = match args.positional.get(2).map(|s| s.as_str()) {
+        Some("full") => Topology::FullyConnected,
+        _ => Topology::Ring,
+    };
+    let mut islands = vec![];
+    for i in 0..n {
+        let mut g = GA::<DeckBTree>::from_rand(ctx.clone());
+        configure(&mut g, args);
+        g.set_mutation_args(10 + 10 * i as isize, 3, 0.05);
+        islands.push(g);
+    }
+    let mut a = Archipelago::new(islands, topology);
+    a.set_migration_args(10, 5);
+    while a.tick() {}
+    println!("\nHALL OF FAME");
+    let hall_of_fame = a.hall_of_fame();
+    println!("{}", hall_of_fame.as_string(&ctx));
+    println!("{}", hall_of_fame_distances(&ctx, &hall_of_fame));
+}
+
+fn run_pareto(ctx: Context, args: &Args) {
+    let generations = args.get(0, 100.0);
+    let mut p = NSGA2::<DeckBTree>::from_rand(ctx, 512);
+    p.set_fitness(Fitness::from_weights(1.0, 1.0, 1.0));
+    p.set_mutation_args(20, 3, 0.05);
+    p.set_stop_condition(generations);
+    while p.tick() {}
+    println!("\nPARETO FRONT");
+    println!("{}", p.front_as_string());
+}
+
+// Reads a deck given either as a url, or as a file containing a deck. The card names corrected in
+// the file are printed.
+fn read_deck(ctx: &Context, source: &str) -> DeckBTree {
+    if source.contains("/deck/") {
+        let mut deck = from_url(ctx, source).unwrap();
+        deck.repair(ctx);
+        deck
+    } else {
+        let (decks, corrections) = read_decks(ctx, source).unwrap();
+        for correction in corrections {
+            println!("Reading {}", correction);
+        }
+        decks.into_iter().next().expect("no deck in the file")
+    }
+}
+
+// Prints the given deck as a decklist and as a url.
+fn run_convert(ctx: Context, args: &Args) {
+    let deck = read_deck(&ctx, args.positional.get(1).expect("missing deck url or file"));
+    println!("\n{}", to_list(&ctx, &deck));
+    println!("{}", deck.url(&ctx));
+    println!("{}", composition(&ctx, &deck, args));
+    export_deck(&ctx, &fitness(&ctx, args), &deck, args);
+}
+
+// Compares two decks, for example a GA deck and a tournament list.
+fn run_diff(ctx: Context, args: &Args) {
+    let a = read_deck(&ctx, args.positional.get(1).expect("missing first deck url or file"));
+    let b = read_deck(&ctx, args.positional.get(2).expect("missing second deck url or file"));
+    println!("\n{}", diff(&ctx, &a, &b));
+}
+
+// Prints the odds of drawing cards of a group by each turn.
+fn run_odds(ctx: Context, args: &Args) {
+    let deck = read_deck(&ctx, args.positional.get(1).expect("missing deck url or file"));
+    let group = Group::parse(&ctx, args.positional.get(2).expect("missing card group")).unwrap();
+    println!("\n{}", odds_as_string(&ctx, &deck, &group, args.get(2, 1), args.get(3, 6)));
+}
+
+// Plays the deck alone, to see how often it can spend all of its pp on each turn.
+fn run_goldfish(ctx: Context, args: &Args) {
+    let deck = read_deck(&ctx, args.positional.get(1).expect("missing deck url or file"));
+    let goldfish = Goldfish { games: args.get(1, 10_000), turns: args.get(2, 10) };
+    if goldfish.games == 0 || goldfish.turns == 0 {
+        panic!("the numbers of games and turns must be positive");
+    }
+    println!("\n{}", goldfish.as_string(&ctx, &deck));
+}
+
+// Prints the pairs of cards that the decks of the corpus play together the most.
+fn run_synergy(ctx: Context, args: &Args) {
+    let decks = read_corpus(&ctx, args.positional.get(1).expect("missing corpus directory or file"));
+    let synergy = Synergy::from_decks(&decks.unwrap());
+    println!("\n{}", synergy.as_string(&ctx, args.get(1, 20), args.get(2, 2)));
+}
+
+// Learns the curves, card types and tag densities of the decks of the corpus, for each archetype,
+// and saves them as fitness targets.
+fn run_targets(ctx: Context, args: &Args) {
+    let decks = read_corpus(&ctx, args.positional.get(1).expect("missing corpus directory or file"));
+    let targets = learn(&ctx, &decks.unwrap()).unwrap();
+    for t in &targets {
+        println!("\n{}", t.as_string());
+    }
+    let path = args.get(1, String::from("targets.json"));
+    save(&targets, &path).unwrap();
+    println!("\nTargets saved to {}", path);
+}
+
+// Searches for better decks that can be reached from the given one by swapping at most budget
+// cards.
+fn run_upgrade(ctx: Context, args: &Args) {
+    let deck = read_deck(&ctx, args.positional.get(1).expect("missing deck url or file"));
+    let budget = args.get(1, 5);
+    let mut g = GA::<DeckBTree>::from_rand(ctx);
+    configure(&mut g, args);
+    g.set_hall_of_fame_args(5, 2);
+    g.set_anchor(deck.clone(), budget);
+    while g.tick() {}
+    println!("\nSUGGESTED UPGRADES");
+    for (i, (upgrade, _)) in g.hall_of_fame.entries.iter().enumerate() {
+        println!("\n#{} - {}", i + 1, report(&g.ctx, &g.fitness, &deck, upgrade));
+        println!("{}", upgrade.url(&g.ctx));
+    }
+}
+
+// Runs one of the optimizers for a given number of seconds, or until it stops on its own.
+fn run_optimizer(ctx: Context, args: &Args) {
+    // every optimizer rates decks the same way, so that their best fitnesses can be compared.
+    let fitness = fitness(&ctx, args);
+    let mut optimizer: Box<dyn Optimizer<DeckBTree>> = match args.positional.get(1).map(|s| s.as_str()) {
+        Some("sa") => Box::new(SimulatedAnnealing::from_rand(ctx, fitness, 0.05, 0.9999, 0.0001)),
+        Some("hill") => Box::new(HillClimbing::from_rand(ctx, fitness, 100)),
+        Some("tabu") => Box::new(Tabu::from_rand(ctx, fitness, 20, 1_000)),
+        _ => {
+            let mut g = GA::<DeckBTree>::from_rand(ctx);
+            configure_with(&mut g, args, fitness);
+            Box::new(g)
+        }
+    };
+    let budget = args.positional.get(2).and_then(|s| s.parse().ok()).map(Duration::from_secs_f64);
+    let (deck, score) = run(optimizer.as_mut(), budget);
+    println!("\nBEST DECK - fitness: {}", score);
+    println!("{}", deck.as_string(optimizer.ctx()));
+    println!("{}", deck.url(optimizer.ctx()));
+    println!("{}", composition(optimizer.ctx(), &deck, args));
+    export_deck(optimizer.ctx(), optimizer.fitness(), &deck, args);
+}
+
+// Finds the best deck for the linear part of the fitness (the tags and length scores), then
+// measures how close to it the GA gets.
+fn run_exact(ctx: Context, args: &Args) {
+    let (w_tags, w_length) = (args.get(0, RATE_ARGS.1), args.get(1, RATE_ARGS.2));
+    let generations: f64 = args.get(2, 200.0);
+    let fitness = || Fitness::from_weights(0.0, w_tags, w_length);
+    let (optimum, score) = solve(&ctx, &fitness()).unwrap();
+    let mut g = GA::<DeckBTree>::from_rand(ctx);
+    configure(&mut g, args);
+    g.set_fitness(fitness());
+    // random decks score far below the usual cull thresholds on this fitness, so selection alone
+    // drives the search.
+    g.set_cull_args(0.0, 0.0, 0.0);
+    g.set_stop_condition(score);
+    while g.time < generations && g.tick() {}
+    let ga_score = g.max_scores.iter().fold(f64::MIN, |a, b| a.max(*b));
+    println!("\nOPTIMUM - fitness: {}", score);
+    println!("{}", optimum.as_string(&g.ctx));
+    println!("{}", optimum.url(&g.ctx));
+    println!("\nBest GA fitness: {} (gap: {}, {:.2}%)", ga_score, score - ga_score,
+             100.0 * (score - ga_score) / score);
+}
+
+// Runs short GAs with many combinations of settings, and saves the best one as a preset.
+fn run_tuning(ctx: Context, args: &Args) {
+    let space = Space::default();
+    let presets = match args.positional.get(1).map(|s| s.as_str()) {
+        Some("random") => space.sample(args.option("samples", 10)),
+        _ => space.grid(),
+    };
+    let (generations, repeats) = (args.get(1, 30.0), args.get(2, 3));
+    let reference = fitness(&ctx, args);
+    let mut trials = vec![];
+    for (i, preset) in presets.iter().enumerate() {
+        println!("\nTUNING {}/{}: {:?}", i + 1, presets.len(), preset);
+        let base = || {
+            let mut g = GA::<DeckBTree>::from_rand(ctx.clone());
+            configure(&mut g, args);
+            g
+        };
+        trials.push(evaluate(base, preset, &reference, repeats, generations,
+                             args.option("target", 0.9)));
+    }
+    rank(&mut trials);
+    println!("\nRANKING");
+    println!("{}", trials_as_string(&trials, repeats));
+    let path = args.option("out", String::from("preset.json"));
+    trials[0].preset.save(&path).unwrap();
+    println!("Best preset saved to {}, use it with --preset {}", path, path);
+}
+
+// Runs the GA with the cards ordered in each way, and compares how fast they find good decks: by
+// the best fitness reached at fixed generations, and by the generations taken to reach the target.
+fn run_loci(ctx: Context, args: &Args) {
+    let mut names = vec!["attributes", "shuffled", "tags", "roles", "text"];
+    if args.options.contains_key("corpus") {
+        names.push("cooccurrence");
+    }
+    let (generations, repeats) = (args.get(0, 50.0), args.get(1, 3));
+    let mut results = vec![];
+    for name in names {
+        let ordering = ordering(&ctx, name, args);
+        // the fitness is built once per ordering, and serves as the reference too.
+        let (ctx, fitness) = reordered(&ctx, &*ordering, |ctx| fitness(ctx, args));
+        println!("\nORDERING: {}", ordering.name());
+        let base = || {
+            let mut g = GA::<DeckBTree>::from_rand(ctx.clone());
+            configure_with(&mut g, args, fitness.clone());
+            g
+        };
+        let trial = evaluate(base, &preset(args), &fitness, repeats, generations,
+                             args.option("target", 0.9));
+        results.push((ordering.name(), trial));
+    }
+    let checkpoints: Vec<String> = checkpoints(generations).iter().map(|c| c.to_string()).collect();
+    println!("\nORDERINGS (best by generation {})", checkpoints.join(", "));
+    for (name, t) in results {
+        let progress: Vec<String> = t.progress.iter().map(|p| format!("{:.4}", p)).collect();
+        println!("{}\tbest: {}\ttime to target: {:.1} ({}/{})", name, progress.join(", "),
+                 t.time_to_target, t.hits, repeats);
+    }
+}
+
+// Usage:
+//  shadowgen                                   runs a single GA.
+//  shadowgen islands [n] [ring|full]           runs n GAs side by side, with migrations.
+//  shadowgen pareto [generations]              optimizes every fitness component separately.
+//  shadowgen upgrade <url|file> [budget]       improves a deck by changing at most budget cards.
+//  shadowgen convert <url|file>                prints the deck as a decklist and as a url.
+//  shadowgen diff <url|file> <url|file>        compares two decks.
+//  shadowgen odds <url|file> <group> [k] [turns] the odds of drawing at least 1 to k cards of the
+//                                              group (tag:<tag>, pp:<pp> or card:<name>) by
+//                                              each turn.
+//  shadowgen goldfish <url|file> [games] [turns] how often the deck spends all its pp each turn.
+//  shadowgen synergy <dir|file> [n] [min_decks] the n pairs of cards most often played together
+//                                              in at least min_decks decks of the corpus.
+//  shadowgen targets <dir|file> [out]          learns fitness targets from the decks of the
+//                                              corpus, for each archetype (see targets.rs).
+//  shadowgen optimize <ga|sa|hill|tabu> [secs] runs the given optimizer, for at most secs seconds.
+//  shadowgen exact [w_tags] [w_length] [generations] finds the best deck for the linear scores,
+//                                              and how far the GA gets from it.
+//  shadowgen tune [grid|random] [generations] [repeats] tries combinations of GA settings
+//                                              and saves the best one (see tuning.rs).
+//  shadowgen loci [generations] [repeats]      compares how fast the GA converges under each
+//                                              locus ordering (see locus.rs).
+// Options:
+//  --seed <file>                               seeds the initial population with the decks in
+//                                              the file (urls or decklists, see decklist.rs).
+//  --seed-fraction <f>                         the fraction of the population that is seeded.
+//  --schedule <linear|adaptive>                how mutation temperature and cull threshold evolve.
+//  --elites <n>                                the number of best decks kept unchanged.
+//  --immigrants <f>                            the fraction of each generation that is random.
+//  --preset <file>                             the GA settings saved by the tune mode.
+//  --curve <plain|effective>                   whether the curve score counts alternate costs.
+//  --types <weight>                            the weight of the card types score (0.1 by default).
+//  --roles <weight>                            adds to the fitness how well the deck covers the
+//                                              role minimums (see fitness.rs and tagger.py).
+//  --role-minimums <role:n,...>                the role minimums (removal:8,draw:4,aoe:2 by default).
+//  --consistency <group>                       adds the odds of drawing the group to the fitness.
+//  --consistency-args <k,turn,weight>          at least k cards by the turn (1,3,0.2 by default).
+//  --curve-out <weight>                        adds to the fitness how often decks spend all their
+//                                              pp, estimated by playing games (see goldfish.rs).
+//  --synergy <dir|file>                        adds to the fitness how often the pairs of cards of
+//                                              the deck are played together in the corpus of
+//                                              decks (urls or decklists, see synergy.rs).
+//  --synergy-weight <weight>                   the weight of the synergy score (0.2 by default).
+//  --targets <file>                            uses the learned targets for the curve, the card
+//                                              types, and the tag densities.
+//  --archetype <name>                          the archetype of the targets, by default the first
+//                                              one named after a chosen tag, or "All".
+//  --tag-densities <weight>                    the weight of the tag densities score (0.2 by
+//                                              default).
+//  --ordering <name>                           how the cards are ordered in the genome: attributes
+//                                              (the default), shuffled, or a seriation by tags,
+//                                              roles, text or cooccurrence.
+//  --corpus <dir|file>                         the decks the cooccurrence ordering learns from.
+//  --export <file>                             writes the best deck with its card metadata, as
+//                                              CSV if the file ends in .csv, as JSON otherwise.
+//  --samples <n>, --target <f>, --out <file>   for the tune mode: the number of random
+//                                              combinations, the fitness runs try to reach, and
+//                                              where the best preset is saved.
+fn main() {
+    let mut args = Args::from_env();
+    let mut ctx = Context::from_input();
+    if let Some(name) = args.options.get("ordering") {
+        let ordering = ordering(&ctx, name, &args);
+        ctx.reorder(&*ordering);
+    }
+    args.targets = targets(&ctx, &args);
+    if let Some(targets) = &args.targets {
+        println!("Targets: {}", targets.as_string());
+    }
+    match args.mode() {
+        Some("islands") => run_islands(ctx, &args),
+        Some("pareto") => run_pareto(ctx, &args),
+        Some("upgrade") => run_upgrade(ctx, &args),
+        Some("convert") => run_convert(ctx, &args),
+        Some("diff") => run_diff(ctx, &args),
+        Some("odds") => run_odds(ctx, &args),
+        Some("goldfish") => run_goldfish(ctx, &args),
+        Some("synergy") => run_synergy(ctx, &args),
+        Some("targets") => run_targets(ctx, &args),
+        Some("optimize") => run_optimizer(ctx, &args),
+        Some("exact") => run_exact(ctx, &args),
+        Some("tune") => run_tuning(ctx, &args),
+        Some("loci") => run_loci(ctx, &args),
+        _ => run_ga(ctx, &args),
+    }
     println!("\nEnter any key to exit.");
     std::io::stdin().read_line(&mut String::new()).unwrap();
-}
\ No newline at end of file
+}
@@ -0,0 +1,192 @@
+use crate::context::Context;
+use crate::deck::{Deck, DeckBTree, PP_CURVE_SIZE};
+use crate::decklist::find_card;
+use crate::fitness::FitnessComponent;
+
+// The size of the opening hand, which can be mulliganed.
+pub const OPENING_HAND: usize = 3;
+
+// The cards whose draws are counted.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Group {
+    Tag(String),
+    // A bucket of the pp curve, see Deck::pp_curve.
+    Pp(usize),
+    Card(usize),
+}
+
+impl Group {
+    // Reads "tag:<tag>", "pp:<bucket>" or "card:<card name>".
+    pub fn parse(ctx: &Context, s: &str) -> Result<Group, String> {
+        let (kind, value) = s.split_once(':').ok_or(format!("invalid card group: {}", s))?;
+        let invalid = |_| format!("invalid card group: {}", s);
+        match kind {
+            "tag" => Ok(Group::Tag(value.to_string())),
+            "pp" => Ok(Group::Pp(value.parse().map_err(invalid)?)),
+            "card" => Ok(Group::Card(find_card(ctx, value)?.0)),
+            _ => Err(format!("invalid card group: {}", s)),
+        }
+    }
+
+    pub fn contains(&self, ctx: &Context, idx: usize) -> bool {
+        match self {
+            Group::Tag(tag) => ctx.idx_to_card(idx).tags_.contains(tag),
+            Group::Pp(bucket) => {
+                let pp = ctx.idx_to_card(idx).pp_ as usize;
+                pp.clamp(1, PP_CURVE_SIZE) == *bucket.max(&1)
+            }
+            Group::Card(card) => idx == *card,
+        }
+    }
+
+    // The number of copies in the deck that belong to the group.
+    pub fn count(&self, ctx: &Context, deck: &DeckBTree) -> usize {
+        deck.0.iter()
+            .filter(|(idx, _)| self.contains(ctx, **idx))
+            .map(|(_, qty)| *qty as usize)
+            .sum()
+    }
+
+    pub fn as_string(&self, ctx: &Context) -> String {
+        match self {
+            Group::Tag(tag) => format!("{} cards", tag),
+            Group::Pp(bucket) => format!("{}pp cards", bucket),
+            Group::Card(idx) => ctx.idx_to_card(*idx).name_.clone(),
+        }
+    }
+}
+
+fn binomial(n: usize, k: usize) -> f64 {
+    if k > n { return 0.0; }
+    (0..k.min(n - k)).fold(1.0, |acc, i| acc * (n - i) as f64 / (i + 1) as f64)
+}
+
+// The probability of exactly k successes in n draws without replacement, from a population of
+// size with hits successes.
+pub fn hypergeometric(size: usize, hits: usize, n: usize, k: usize) -> f64 {
+    if hits > size || n > size || k > n || k > hits || n - k > size - hits { return 0.0; }
+    binomial(hits, k) * binomial(size - hits, n - k) / binomial(size, n)
+}
+
+fn at_least(size: usize, hits: usize, n: usize, k: usize) -> f64 {
+    (k..=n.min(hits)).map(|i| hypergeometric(size, hits, n, i)).sum::<f64>().min(1.0)
+}
+
+// The number of cards drawn after the opening hand by the given turn. The player going second
+// draws an extra card on their first turn.
+pub fn draws(turn: usize, going_first: bool) -> usize {
+    if going_first { turn } else { turn + 1 }
+}
+
+// The probability of having drawn at least k cards of a group of hits cards, out of a deck of
+// size, by the given turn.
+// When mulliganing, every card of the opening hand that isn't in the group is swapped: the new
+// cards are drawn first, then the swapped ones are shuffled back in the deck.
+pub fn draw_odds(size: usize, hits: usize, k: usize, turn: usize, going_first: bool,
+                 mulligan: bool) -> f64 {
+    let draws = draws(turn, going_first);
+    if !mulligan {
+        return at_least(size, hits, OPENING_HAND + draws, k);
+    }
+    let mut ret = 0.0;
+    for kept in 0..=OPENING_HAND.min(hits) {
+        let p_kept = hypergeometric(size, hits, OPENING_HAND, kept);
+        let swapped = OPENING_HAND - kept;
+        for redrawn in 0..=swapped.min(hits - kept) {
+            let p_redrawn = hypergeometric(size - OPENING_HAND, hits - kept, swapped, redrawn);
+            let in_hand = kept + redrawn;
+            let p_rest = at_least(size - OPENING_HAND, hits - in_hand, draws, k.saturating_sub(in_hand));
+            ret += p_kept * p_redrawn * p_rest;
+        }
+    }
+    ret.min(1.0)
+}
+
+// The odds of drawing at least 1 to k cards of the group, going first and second, turn by turn.
+pub fn odds_as_string(ctx: &Context, deck: &DeckBTree, group: &Group, k: usize, turns: usize)
+                      -> String {
+    let (size, hits) = (deck.len() as usize, group.count(ctx, deck));
+    let mut ret = format!("{}: {} copies, mulliganing everything else\n", group.as_string(ctx), hits);
+    for at_least in 1..=k {
+        ret.push_str(&format!("At least {}:\n\tturn\tfirst\tsecond\n", at_least));
+        for turn in 1..=turns {
+            ret.push_str(&format!("\t{}\t{:.1}%\t{:.1}%\n", turn,
+                                  100.0 * draw_odds(size, hits, at_least, turn, true, true),
+                                  100.0 * draw_odds(size, hits, at_least, turn, false, true)));
+        }
+    }
+    ret
+}
+
+// The probability of drawing at least k cards of the group by the given turn, mulliganing, as the
+// mean of going first and second.
+pub struct Consistency {
+    pub group: Group,
+    pub k: usize,
+    pub turn: usize,
+}
+
+impl FitnessComponent for Consistency {
+    fn name(&self) -> String {
+        String::from("consistency")
+    }
+
+    fn score(&self, ctx: &Context, deck: &DeckBTree) -> f64 {
+        let (size, hits) = (deck.len() as usize, self.group.count(ctx, deck));
+        (draw_odds(size, hits, self.k, self.turn, true, true)
+            + draw_odds(size, hits, self.k, self.turn, false, true)) / 2.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::Context;
+    use crate::deck::{Deck, DeckBTree};
+    use crate::fitness::FitnessComponent;
+    use crate::odds::{Consistency, draw_odds, Group, hypergeometric};
+
+    #[test]
+    fn distribution() {
+        // 2 aces out of 4 cards drawn from a 52 cards deck.
+        assert!(f64::abs(hypergeometric(52, 4, 4, 2) - 0.0249) < 1e-4);
+        let total: f64 = (0..=3).map(|k| hypergeometric(40, 9, 3, k)).sum();
+        assert!(f64::abs(total - 1.0) < 1e-9);
+        assert_eq!(hypergeometric(40, 2, 3, 3), 0.0);
+    }
+
+    #[test]
+    fn odds() {
+        // without mulligan, 3 copies in the 4 cards seen going first on turn 1.
+        let no_hit = hypergeometric(40, 3, 4, 0);
+        assert!(f64::abs(draw_odds(40, 3, 1, 1, true, false) - (1.0 - no_hit)) < 1e-9);
+        // going second, and mulliganing, improve the odds.
+        let first = draw_odds(40, 3, 1, 1, true, false);
+        assert!(draw_odds(40, 3, 1, 1, false, false) > first);
+        assert!(draw_odds(40, 3, 1, 1, true, true) > first);
+        // more turns improve them too.
+        assert!(draw_odds(40, 3, 1, 5, true, true) > draw_odds(40, 3, 1, 2, true, true));
+        assert_eq!(draw_odds(40, 40, 2, 1, true, true), 1.0);
+        assert_eq!(draw_odds(40, 0, 1, 10, true, true), 0.0);
+        // with mulligan, the odds of at least 0 are still exactly 1.
+        assert!(f64::abs(draw_odds(40, 9, 0, 3, true, true) - 1.0) < 1e-9);
+    }
+
+    #[test]
+    fn groups() {
+        let ctx = Context::from_debug();
+        assert_eq!(Group::parse(&ctx, "tag:Buff"), Ok(Group::Tag(String::from("Buff"))));
+        assert_eq!(Group::parse(&ctx, "pp:2"), Ok(Group::Pp(2)));
+        let card = format!("card:{}", ctx.idx_to_card(0).name_);
+        assert_eq!(Group::parse(&ctx, &card), Ok(Group::Card(0)));
+        assert!(Group::parse(&ctx, "pp:two").is_err());
+        assert!(Group::parse(&ctx, "Buff").is_err());
+        let d = DeckBTree::from_rand(&ctx);
+        let by_bucket: usize = (1..=8).map(|pp| Group::Pp(pp).count(&ctx, &d)).sum();
+        assert_eq!(by_bucket, d.len() as usize);
+        let (idx, qty) = d.0.iter().next().unwrap();
+        assert_eq!(Group::Card(*idx).count(&ctx, &d), *qty as usize);
+        let c = Consistency { group: Group::Card(*idx), k: 1, turn: 3 };
+        let score = c.score(&ctx, &d);
+        assert!(score > 0.0 && score < 1.0);
+    }
+}
@@ -0,0 +1,94 @@
+use std::time::{Duration, Instant};
+
+use crate::context::Context;
+use crate::deck::Deck;
+use crate::fitness::Fitness;
+use crate::population::GA;
+
+// A search strategy over decks. Each tick is a unit of work of the strategy (a generation, a
+// batch of moves...), after which the best deck found so far can be inspected.
+pub trait Optimizer<D: Deck> {
+    fn name(&self) -> String;
+    fn ctx(&self) -> &Context;
+    fn fitness(&self) -> &Fitness<D>;
+    // Returns false once the optimizer has nothing left to do.
+    fn tick(&mut self) -> bool;
+    // The best deck found so far, with its fitness.
+    fn best(&self) -> (D, f64);
+    // The number of fitness evaluations done so far.
+    fn evaluations(&self) -> u64;
+}
+
+impl<D: Clone + Deck + PartialEq> Optimizer<D> for GA<D> {
+    fn name(&self) -> String {
+        String::from("ga")
+    }
+
+    fn ctx(&self) -> &Context {
+        &self.ctx
+    }
+
+    fn fitness(&self) -> &Fitness<D> {
+        &self.fitness
+    }
+
+    fn tick(&mut self) -> bool {
+        GA::tick(self)
+    }
+
+    fn best(&self) -> (D, f64) {
+        match self.hall_of_fame.entries.first() {
+            Some(entry) => entry.clone(),
+            None => (self.population[0].0.clone(), *self.max_scores.last().unwrap_or(&0.0)),
+        }
+    }
+
+    // Only the decks actually rated count: the scores found in the cache cost nothing.
+    fn evaluations(&self) -> u64 {
+        self.cache.misses
+    }
+}
+
+// Ticks the optimizer until it stops or the time budget runs out. After every tick the same
+// statistics are logged whatever the optimizer, so that different ones can be compared.
+pub fn run<D: Deck>(optimizer: &mut dyn Optimizer<D>, budget: Option<Duration>) -> (D, f64) {
+    let start = Instant::now();
+    let mut iteration = 0;
+    loop {
+        let going = optimizer.tick();
+        iteration += 1;
+        let (_, best) = optimizer.best();
+        println!("[{}] iteration: {}\telapsed: {:.2}s\tevaluations: {}\tbest fitness: {}",
+                 optimizer.name(),
+                 iteration,
+                 start.elapsed().as_secs_f64(),
+                 optimizer.evaluations(),
+                 best);
+        if !going || budget.is_some_and(|b| start.elapsed() >= b) { break; }
+    }
+    optimizer.best()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::context::Context;
+    use crate::deck::DeckBTree;
+    use crate::optimizer::{Optimizer, run};
+    use crate::population::GA;
+
+    #[test]
+    fn ga() {
+        let ctx = Context::from_debug();
+        let mut ga: GA<DeckBTree> = GA::from_rand(ctx);
+        ga.set_rate_args(0.5, 0.0, 0.5);
+        ga.set_mutation_args(10, 3, 0.05);
+        ga.set_cull_args(0.0, 0.0, 0.0);
+        // the time budget runs out after the first tick.
+        let (_, score) = run(&mut ga, Some(Duration::ZERO));
+        assert_eq!(ga.time, 1.0);
+        assert_eq!(score, ga.max_scores[0]);
+        assert_eq!(ga.evaluations(), ga.population.len() as u64);
+    }
+}
@@ -0,0 +1,266 @@
+use std::cmp::Ordering;
+
+use rand::Rng;
+
+use crate::context::Context;
+use crate::deck::Deck;
+use crate::fitness::Fitness;
+
+// Whether a is at least as good as b on every objective, and strictly better on at least one.
+pub fn dominates(a: &[f64], b: &[f64]) -> bool {
+    a.iter().zip(b).all(|(x, y)| x >= y) && a.iter().zip(b).any(|(x, y)| x > y)
+}
+
+// Splits the points in fronts: the first one is made of the non-dominated points, the second one
+// of the points that are non-dominated once the first front is removed, and so on.
+pub fn fronts(points: &[Vec<f64>]) -> Vec<Vec<usize>> {
+    let n = points.len();
+    // dominated[i]: the points dominated by i. counts[i]: the number of points dominating i.
+    let mut dominated = vec![vec![]; n];
+    let mut counts = vec![0; n];
+    for i in 0..n {
+        for j in i + 1..n {
+            if dominates(&points[i], &points[j]) {
+                dominated[i].push(j);
+                counts[j] += 1;
+            } else if dominates(&points[j], &points[i]) {
+                dominated[j].push(i);
+                counts[i] += 1;
+            }
+        }
+    }
+    let mut ret = vec![];
+    let mut current: Vec<usize> = (0..n).filter(|i| counts[*i] == 0).collect();
+    while !current.is_empty() {
+        let mut next = vec![];
+        for &i in &current {
+            for &j in &dominated[i] {
+                counts[j] -= 1;
+                if counts[j] == 0 { next.push(j); }
+            }
+        }
+        ret.push(current);
+        current = next;
+    }
+    ret
+}
+
+// For each point of a front, the sum over the objectives of the distance between its neighbors.
+// Points at the boundaries of the front are given an infinite distance, so that they are always
+// preferred: this keeps the front spread out.
+pub fn crowding_distances(points: &[Vec<f64>], front: &[usize]) -> Vec<f64> {
+    let mut ret = vec![0.0; front.len()];
+    if front.is_empty() { return ret; }
+    // the values of each objective, in the order of the front.
+    let columns = (0..points[front[0]].len())
+        .map(|o| front.iter().map(|i| points[*i][o]).collect::<Vec<f64>>());
+    for values in columns {
+        let mut order: Vec<usize> = (0..front.len()).collect();
+        order.sort_by(|a, b| values[*a].partial_cmp(&values[*b]).unwrap());
+        let lo = values[order[0]];
+        let hi = values[*order.last().unwrap()];
+        ret[order[0]] = f64::INFINITY;
+        ret[*order.last().unwrap()] = f64::INFINITY;
+        if hi - lo <= 0.0 { continue; }
+        for k in 1..order.len().saturating_sub(1) {
+            ret[order[k]] += (values[order[k + 1]] - values[order[k - 1]]) / (hi - lo);
+        }
+    }
+    ret
+}
+
+// An NSGA-II optimizer: instead of maximizing a weighed sum, every fitness component is treated as
+// a separate objective, and the population approaches the set of decks for which no objective
+// can be improved without worsening another (the Pareto front).
+pub struct NSGA2<D>
+    where D: Deck
+{
+    pub ctx: Context,
+    pub fitness: Fitness<D>,
+    // Each deck comes with the score of each objective.
+    pub population: Vec<(D, Vec<f64>)>,
+    // The front index and the crowding distance of each deck, compared in tournaments.
+    ranks: Vec<(usize, f64)>,
+    pub time: f64,
+    pub size: usize,
+    pub mutation_args: (isize, isize, f64),
+    pub generations: f64,
+}
+
+impl<D: Clone + Deck> NSGA2<D> {
+    pub fn from_rand(ctx: Context, size: usize) -> NSGA2<D> {
+        let population = (0..size).map(|_| (D::from_rand(&ctx), vec![])).collect();
+        NSGA2 {
+            ctx,
+            fitness: Fitness::default(),
+            population,
+            ranks: vec![],
+            time: 0.0,
+            size,
+            mutation_args: (0, 0, 0.0),
+            generations: 0.0,
+        }
+    }
+
+    // Every component of the fitness is an objective, regardless of its weight.
+    pub fn set_fitness(&mut self, fitness: Fitness<D>) {
+        self.fitness = fitness;
+    }
+
+    pub fn set_mutation_args(&mut self, temp: isize, starting_temp: isize, temp_annealing: f64) {
+        self.mutation_args = (temp, starting_temp, temp_annealing);
+    }
+
+    pub fn set_stop_condition(&mut self, generations: f64) {
+        self.generations = generations;
+    }
+
+    fn rate(&mut self) {
+        for (deck, objectives) in self.population.iter_mut() {
+            if objectives.is_empty() {
+                *objectives = deck.objectives(&self.ctx, &self.fitness);
+            }
+        }
+    }
+
+    // Keeps the best size decks, front by front, breaking ties in the last front that fits by
+    // crowding distance.
+    fn survive(&mut self) {
+        let points: Vec<Vec<f64>> = self.population.iter().map(|(_, o)| o.clone()).collect();
+        let mut survivors = vec![];
+        for (rank, front) in fronts(&points).into_iter().enumerate() {
+            if survivors.len() >= self.size { break; }
+            let crowding = crowding_distances(&points, &front);
+            let mut front: Vec<(usize, (usize, f64))> = front.into_iter()
+                .zip(crowding)
+                .map(|(i, c)| (i, (rank, c)))
+                .collect();
+            front.sort_by(|(_, (_, a)), (_, (_, b))| b.partial_cmp(a).unwrap());
+            front.truncate(self.size - survivors.len());
+            survivors.extend(front);
+        }
+        let mut population = vec![];
+        self.ranks.clear();
+        for (i, rank) in survivors {
+            population.push(self.population[i].clone());
+            self.ranks.push(rank);
+        }
+        self.population = population;
+    }
+
+    // Binary tournament: the deck in the better front wins, or the less crowded one.
+    fn select(&self) -> usize {
+        let a = rand::thread_rng().gen_range(0, self.ranks.len());
+        let b = rand::thread_rng().gen_range(0, self.ranks.len());
+        let (rank_a, crowding_a) = self.ranks[a];
+        let (rank_b, crowding_b) = self.ranks[b];
+        match rank_a.cmp(&rank_b) {
+            Ordering::Less => a,
+            Ordering::Greater => b,
+            Ordering::Equal => if crowding_a >= crowding_b { a } else { b },
+        }
+    }
+
+    // The decks in the first front, without duplicates.
+    pub fn front(&self) -> Vec<(D, Vec<f64>)> {
+        let mut ret: Vec<(D, Vec<f64>)> = vec![];
+        for ((deck, objectives), (rank, _)) in self.population.iter().zip(&self.ranks) {
+            if *rank == 0 && ret.iter().all(|(d, _)| d.distance(deck) > 0) {
+                ret.push((deck.clone(), objectives.clone()));
+            }
+        }
+        ret
+    }
+
+    // Computes the passage of a unit of time for the population: an offspring as large as the
+    // population is bred, and the best decks among parents and children survive.
+    // Returns false once the given number of generations is reached.
+    pub fn tick(&mut self) -> bool {
+        if self.ranks.is_empty() {
+            self.rate();
+            self.survive();
+        }
+        self.time += 1.0;
+        let (temp, temp_min, temp_annealing) = self.mutation_args;
+        for _ in 0..self.size {
+            let parent = &self.population[self.select()].0;
+            let mut child = parent.mix(&self.population[self.select()].0);
+            child.mutate(&self.ctx, temp, temp_min, temp_annealing, self.time);
+            self.population.push((child, vec![]));
+        }
+        self.rate();
+        self.survive();
+        let front = self.front();
+        println!("\nGENERATION {}", self.time);
+        println!("\tPareto front size: {}", front.len());
+        for (i, name) in self.fitness.names().iter().enumerate() {
+            let best = front.iter().map(|(_, o)| o[i]).fold(f64::MIN, f64::max);
+            println!("\tMax {} score: {}", name, best);
+        }
+        self.time < self.generations
+    }
+
+    pub fn front_as_string(&self) -> String {
+        let names = self.fitness.names();
+        let mut ret = String::new();
+        for (i, (deck, objectives)) in self.front().iter().enumerate() {
+            ret.push_str(&format!("\n#{} -", i + 1));
+            for (name, score) in names.iter().zip(objectives) {
+                ret.push_str(&format!(" {}: {:.4}", name, score));
+            }
+            ret.push_str(&deck.as_string(&self.ctx));
+            ret.push_str(&format!("{}\n", deck.url(&self.ctx)));
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::Context;
+    use crate::deck::DeckBTree;
+    use crate::fitness::Fitness;
+    use crate::pareto::{crowding_distances, dominates, fronts, NSGA2};
+
+    #[test]
+    fn dominance() {
+        assert!(dominates(&[1.0, 0.5], &[0.5, 0.5]));
+        assert!(!dominates(&[0.5, 0.5], &[0.5, 0.5]));
+        assert!(!dominates(&[1.0, 0.0], &[0.0, 1.0]));
+    }
+
+    #[test]
+    fn sorting() {
+        let points = vec![
+            vec![1.0, 0.0],
+            vec![0.0, 1.0],
+            vec![0.5, 0.5],
+            vec![0.4, 0.4],
+            vec![0.1, 0.1],
+        ];
+        assert_eq!(fronts(&points), vec![vec![0, 1, 2], vec![3], vec![4]]);
+        let crowding = crowding_distances(&points, &[0, 1, 2]);
+        assert_eq!(crowding[0], f64::INFINITY);
+        assert_eq!(crowding[1], f64::INFINITY);
+        assert!(f64::abs(crowding[2] - 2.0) < 1e-9);
+    }
+
+    #[test]
+    fn tick() {
+        let ctx = Context::from_debug();
+        let mut nsga: NSGA2<DeckBTree> = NSGA2::from_rand(ctx, 64);
+        nsga.set_fitness(Fitness::from_weights(1.0, 1.0, 1.0));
+        nsga.set_mutation_args(10, 3, 0.05);
+        nsga.set_stop_condition(2.0);
+        assert!(nsga.tick());
+        assert!(!nsga.tick());
+        assert_eq!(nsga.population.len(), 64);
+        let front = nsga.front();
+        assert!(!front.is_empty());
+        for (_, a) in &front {
+            for (_, b) in &nsga.population {
+                assert!(!dominates(b, a));
+            }
+        }
+    }
+}
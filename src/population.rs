@@ -1,32 +1,67 @@
+use std::mem;
+use std::slice;
+
+use fnv::FnvHashSet;
 use rand::Rng;
+use rand::seq::index;
 
+use crate::cache::FitnessCache;
 use crate::context::Context;
-use crate::deck::Deck;
+use crate::deck::{Deck, DECK_SIZE, DeckBTree};
+use crate::fitness::Fitness;
+use crate::hall_of_fame::HallOfFame;
+use crate::schedule::{Linear, Progress, Schedule};
 
 pub const POPULATION_SIZE: usize = 2_048;
+// The maximum number of mutations applied to the copies of a seed deck.
+pub const SEED_MUTATIONS: usize = 3;
+// The mutation temperature used on the copies of a seed deck.
+pub const SEED_TEMP: isize = 5;
+// The number of decks each survivor is compared against to estimate its niche count.
+pub const NICHE_SAMPLE_SIZE: usize = 64;
+// The number of pairs of decks compared to estimate the diversity of the population.
+pub const DIVERSITY_SAMPLE_SIZE: usize = 64;
 
 pub struct GA<D>
     where D: Deck
 {
+    // Prefixes the GA's logs, to tell apart GAs running side by side.
+    pub name: String,
     pub ctx: Context,
     pub population: Vec<(D, f64)>,
     pub time: f64,
     pub min_scores: Vec<f64>,
     pub avg_scores: Vec<f64>,
     pub max_scores: Vec<f64>,
-    pub rate_args: (f64, f64, f64),
+    // The mean distance between pairs of decks, in swaps and between tag profiles, and the number
+    // of distinct decks, of each generation.
+    pub mean_distances: Vec<f64>,
+    pub mean_tag_distances: Vec<f64>,
+    pub unique_decks: Vec<usize>,
+    pub fitness: Fitness<D>,
     pub mutation_args: (isize, isize, f64),
     pub cull_args: (f64, f64, f64),
+    // Turns the mutation and cull args into the mutation temperature and cull threshold of the
+    // current generation, which are stored in params.
+    pub schedule: Box<dyn Schedule>,
+    pub params: (isize, f64),
+    pub niching_args: (i8, f64),
+    pub replacement_args: (usize, f64),
+    pub cache: FitnessCache<D>,
     pub target_fitness: f64,
+    pub hall_of_fame: HallOfFame<D>,
+    // A deck that every individual must stay within a given distance of.
+    pub anchor: Option<(D, i8)>,
 }
 
-impl<D: Clone + Deck> GA<D> {
+impl<D: Clone + Deck + PartialEq> GA<D> {
     pub fn from_rand(ctx: Context) -> GA<D> {
         let mut population = vec![(Deck::new(), 0.0); POPULATION_SIZE];
         for i in 0..POPULATION_SIZE {
             population[i].0 = D::from_rand(&ctx);
         }
         GA {
+            name: String::new(),
             ctx,
             population,
             time: 0.0,
@@ -34,13 +69,66 @@ impl<D: Clone + Deck> GA<D> {
             min_scores: vec![],
             avg_scores: vec![],
             max_scores: vec![],
-            rate_args: (0.0, 0.0, 0.0),
+            mean_distances: vec![],
+            mean_tag_distances: vec![],
+            unique_decks: vec![],
+            fitness: Fitness::default(),
             mutation_args: (0, 0, 0.0),
             cull_args: (0.0, 0.0, 0.0),
+            schedule: Box::new(Linear),
+            params: (0, 0.0),
+            niching_args: (0, 0.0),
+            replacement_args: (1, 0.0),
+            cache: FitnessCache::new(4 * POPULATION_SIZE),
+            hall_of_fame: HallOfFame::new(0, 0),
+            anchor: None,
         }
     }
-    pub fn set_rate_args(&mut self, w_curve: f64, w_tags: f64, w_length: f64) {
-        self.rate_args = (w_curve, w_tags, w_length);
+    // Replaces a fraction of the population with copies of the given decks. Only the first copy
+    // of each deck is left untouched, the others are slightly mutated for diversity.
+    // NOTE: assumes that the decks are full.
+    pub(crate) fn seed(&mut self, decks: &[D], fraction: f64) {
+        if decks.is_empty() { return; }
+        let n = ((fraction * POPULATION_SIZE as f64) as usize).min(POPULATION_SIZE);
+        for i in 0..n {
+            let mut deck = decks[i % decks.len()].clone();
+            if i >= decks.len() {
+                for _ in 0..rand::thread_rng().gen_range(1, SEED_MUTATIONS + 1) {
+                    deck.mutate(&self.ctx, SEED_TEMP, SEED_TEMP, 0.0, 0.0);
+                }
+            }
+            self.population[i] = (deck, 0.0);
+        }
+    }
+
+    // Restricts the search to the decks that differ from the given one by at most budget cards.
+    // The whole population is replaced by mutations of the deck.
+    pub(crate) fn set_anchor(&mut self, deck: D, budget: i8) {
+        self.seed(slice::from_ref(&deck), 1.0);
+        self.anchor = Some((deck, budget));
+        for i in 0..POPULATION_SIZE {
+            let mut deck = self.population[i].0.clone();
+            self.constrain(&mut deck);
+            self.population[i].0 = deck;
+        }
+    }
+
+    // Brings the deck back within the distance allowed by the anchor.
+    fn constrain(&self, deck: &mut D) {
+        if let Some((anchor, budget)) = &self.anchor {
+            while deck.distance(anchor) > *budget {
+                deck.approach(anchor);
+            }
+        }
+    }
+
+    pub(crate) fn set_name(&mut self, name: &str) {
+        self.name = name.to_string();
+    }
+
+    pub(crate) fn set_fitness(&mut self, fitness: Fitness<D>) {
+        self.fitness = fitness;
+        self.cache.clear();
     }
 
     pub fn set_mutation_args(&mut self, temp: isize, starting_temp: isize, temp_annealing: f64) {
@@ -51,16 +139,35 @@ impl<D: Clone + Deck> GA<D> {
         self.cull_args = (threshold, cap, annealing);
     }
 
+    pub(crate) fn set_schedule(&mut self, schedule: Box<dyn Schedule>) {
+        self.schedule = schedule;
+    }
+
+    // A radius of 0 disables niching.
+    pub(crate) fn set_niching_args(&mut self, radius: i8, alpha: f64) {
+        self.niching_args = (radius, alpha);
+    }
+
+    // elites: the number of best decks that are copied unchanged in the next generation. At least
+    // one is kept, because the last child is bred from the best deck alone (see tick).
+    // immigrants: the fraction of each generation that is replaced by random decks.
+    pub(crate) fn set_replacement_args(&mut self, elites: usize, immigrants: f64) {
+        self.replacement_args = (elites.clamp(1, POPULATION_SIZE), immigrants);
+    }
+
     pub(crate) fn set_stop_condition(&mut self, target_fitness: f64) {
         self.target_fitness = target_fitness;
     }
-    // Culls individuals whose fitness is below a threshold that's proportional to time.
+
+    pub(crate) fn set_hall_of_fame_args(&mut self, capacity: usize, min_distance: i8) {
+        self.hall_of_fame = HallOfFame::new(capacity, min_distance);
+    }
+    // Culls individuals whose fitness is below a threshold given by the schedule.
     // Culled decks are placed at the end of the list. The index of the last survivor is returned.
     // NOTE: assumes that the population is sorted by score.
     fn cull(&mut self) -> usize {
-        let (threshold, cap, annealing) = self.cull_args;
-        let threshold = (threshold + (annealing * self.time)).min(cap);
-        println!("Culling decks below {}...", threshold);
+        let threshold = self.params.1;
+        println!("{}Culling decks below {}...", self.name, threshold);
         let mut lo = 0;
         let mut hi = POPULATION_SIZE - 1;
         loop {
@@ -74,6 +181,60 @@ impl<D: Clone + Deck> GA<D> {
         }
     }
 
+    // Fitness sharing: the score of each survivor is divided by its niche count, an estimate of
+    // how crowded its surroundings are, so that the population can keep several archetypes alive
+    // instead of converging on one. Survivors are then sorted again by their shared score.
+    // The raw scores are left in the statistics and in the hall of fame.
+    fn share(&mut self, selectable: usize) {
+        let (radius, alpha) = self.niching_args;
+        if radius <= 0 { return; }
+        let survivors = selectable + 1;
+        // Comparing every pair of decks is too slow, so the niche count is extrapolated from
+        // a sample of the survivors.
+        let sample = if survivors <= NICHE_SAMPLE_SIZE {
+            (0..survivors).collect()
+        } else {
+            index::sample(&mut rand::thread_rng(), survivors, NICHE_SAMPLE_SIZE).into_vec()
+        };
+        let mut shared = Vec::with_capacity(survivors);
+        for i in 0..survivors {
+            let mut niche_count = 0.0;
+            let mut compared = 0;
+            for &j in &sample {
+                if i == j { continue; }
+                compared += 1;
+                let distance = self.population[i].0.distance(&self.population[j].0);
+                if distance < radius {
+                    niche_count += 1.0 - (distance as f64 / radius as f64).powf(alpha);
+                }
+            }
+            // the deck itself always counts as a member of its niche.
+            niche_count = 1.0 + niche_count * (survivors - 1) as f64 / compared as f64;
+            shared.push(self.population[i].1 / niche_count);
+        }
+        for (i, score) in shared.into_iter().enumerate() {
+            self.population[i].1 = score;
+        }
+        self.population[..survivors].sort_unstable_by(|(_, a), (_, b)| a.partial_cmp(b)
+            .unwrap().reverse());
+    }
+
+    // The mean swap and tag distances between random pairs of decks.
+    fn mean_distance(&self) -> (f64, f64) {
+        let mut ret = (0.0, 0.0);
+        for _ in 0..DIVERSITY_SAMPLE_SIZE {
+            let a = &self.population[rand::thread_rng().gen_range(0, POPULATION_SIZE)].0;
+            let b = &self.population[rand::thread_rng().gen_range(0, POPULATION_SIZE)].0;
+            ret.0 += a.distance(b) as f64;
+            ret.1 += a.tag_distance(&self.ctx, b);
+        }
+        (ret.0 / DIVERSITY_SAMPLE_SIZE as f64, ret.1 / DIVERSITY_SAMPLE_SIZE as f64)
+    }
+
+    fn count_unique_decks(&self) -> usize {
+        self.population.iter().map(|(d, _)| d.key()).collect::<FnvHashSet<u64>>().len()
+    }
+
     // Using stochastic acceptance.
     fn select(&self, selectable: usize) -> usize {
         // selectable: the production of a new generation is done in place: candidate parents will
@@ -86,31 +247,50 @@ impl<D: Clone + Deck> GA<D> {
         }
     }
 
-    fn update_params(&mut self) {
+    pub(crate) fn update_params(&mut self) {
         self.time += 1.0;
-        let (w_curve, w_tags, w_length) = self.rate_args;
+        let (hits, misses) = (self.cache.hits, self.cache.misses);
         let mut scores = [0.0; POPULATION_SIZE];
         for i in 0..POPULATION_SIZE {
-            let score = self.population[i].0.rate(&self.ctx, w_curve, w_length, w_tags);
+            let score = self.cache.rate(&self.population[i].0, &self.ctx, &self.fitness);
             self.population[i].1 = score;
             scores[i] = score
         }
         self.population.sort_unstable_by(|(_, a), (_, b)| a.partial_cmp(b)
             .unwrap().reverse());
+        self.hall_of_fame.update(&self.population);
         let min = self.population[POPULATION_SIZE - 1].1;
         let avg = scores.iter().fold(0.0, |s, el| s + el) / POPULATION_SIZE as f64;
         let max = self.population[0].1;
         self.min_scores.push(min);
         self.avg_scores.push(avg);
         self.max_scores.push(max);
-        println!("\nGENERATION {}", self.time);
+        let (swaps, tags) = self.mean_distance();
+        self.mean_distances.push(swaps);
+        self.mean_tag_distances.push(tags);
+        self.unique_decks.push(self.count_unique_decks());
+        let progress = Progress {
+            time: self.time,
+            max_scores: &self.max_scores,
+            diversity: self.mean_distances.last().unwrap() / DECK_SIZE as f64,
+        };
+        self.params = self.schedule.params(self.mutation_args, self.cull_args, &progress);
+        println!("\n{}GENERATION {}", self.name, self.time);
         println!(
             "\tMin fitness: {}\n\
             \tAvg fitness: {}\n\
-            \tMax fitness: {}",
+            \tMax fitness: {}\n\
+            \tMean pairwise distance: {:.2} swaps, {:.3} between tag profiles\n\
+            \tUnique decks: {}\n\
+            \tCache hits/misses: {}/{}",
             self.min_scores.last().unwrap(),
             self.avg_scores.last().unwrap(),
-            self.max_scores.last().unwrap());
+            self.max_scores.last().unwrap(),
+            self.mean_distances.last().unwrap(),
+            self.mean_tag_distances.last().unwrap(),
+            self.unique_decks.last().unwrap(),
+            self.cache.hits - hits,
+            self.cache.misses - misses);
     }
 
     // selects two parents and inserts the resulting children in the new generation. note that this
@@ -120,11 +300,9 @@ impl<D: Clone + Deck> GA<D> {
         // new_population_idx: the index after which the new generation of decks begins.
         let parent = &self.population[self.select(selectable)].0;
         let mut child = parent.mix(&self.population[self.select(selectable)].0);
-        child.mutate(&self.ctx,
-                     self.mutation_args.0,
-                     self.mutation_args.1,
-                     self.mutation_args.2,
-                     self.time);
+        // the temperature is already annealed by the schedule.
+        child.mutate(&self.ctx, self.params.0, self.params.0, 0.0, self.time);
+        self.constrain(&mut child);
         self.population[new_population_idx] = (child, 0.0);
         // REMINDER: now new_population_idx points to the first new gen deck.
     }
@@ -142,29 +320,81 @@ impl<D: Clone + Deck> GA<D> {
 
     pub fn tick(&mut self) -> bool {
         self.update_params();
-        if self.max_scores.last().unwrap() >= &self.target_fitness { return false; }
+        if self.reached_target() { return false; }
+        self.reproduce()
+    }
+
+    pub(crate) fn reached_target(&self) -> bool {
+        self.max_scores.last().unwrap() >= &self.target_fitness
+    }
+
+    // Replaces the rated population with a new generation. Returns false if there weren't enough
+    // survivors to do so.
+    pub(crate) fn reproduce(&mut self) -> bool {
         let selectable = self.cull();
         if selectable < 2 {
             println!(
-                "Too many individuals were culled \
-            for the program to continue.\n"
+                "{}Too many individuals were culled \
+            for the program to continue.\n", self.name
             );
             return false;
         }
-        println!("...{:.2}% of the population left", 100.0 * ((selectable + 1) as f64 / POPULATION_SIZE as f64));
-        for i in (1..POPULATION_SIZE).rev() {
+        println!("{}...{:.2}% of the population left", self.name,
+                 100.0 * ((selectable + 1) as f64 / POPULATION_SIZE as f64));
+        self.share(selectable);
+        let (elites, immigrants) = self.replacement_args;
+        let immigrants = ((immigrants * POPULATION_SIZE as f64) as usize).min(POPULATION_SIZE - elites);
+        // the immigrants take the place of the worst decks, before they can be picked as parents.
+        for i in POPULATION_SIZE - immigrants..POPULATION_SIZE {
+            let mut deck = D::from_rand(&self.ctx);
+            self.constrain(&mut deck);
+            self.population[i] = (deck, 0.0);
+        }
+        for i in (elites..POPULATION_SIZE - immigrants).rev() {
             self.replace_individual(selectable.min(i), i);
         }
         return true;
     }
+
+    // Clones of the n best decks, to be sent to another population.
+    // NOTE: assumes that the population is sorted by score.
+    pub(crate) fn emigrants(&self, n: usize) -> Vec<D> {
+        self.population.iter().take(n).map(|(d, _)| d.clone()).collect()
+    }
+
+    // Replaces the worst decks with the incoming ones, rated according to this GA's parameters.
+    // NOTE: assumes that the population is sorted by score.
+    pub(crate) fn immigrate(&mut self, decks: Vec<D>) {
+        let n = decks.len().min(POPULATION_SIZE);
+        for (i, deck) in decks.into_iter().take(n).enumerate() {
+            let score = self.cache.rate(&deck, &self.ctx, &self.fitness);
+            self.population[POPULATION_SIZE - n + i] = (deck, score);
+        }
+        self.population.sort_unstable_by(|(_, a), (_, b)| a.partial_cmp(b)
+            .unwrap().reverse());
+    }
 }
 
+// The default components of the fitness are written for DeckBTree, see the fitness module.
+impl GA<DeckBTree> {
+    // Sets the weights of the curve, tags and length components, adding the ones that are missing.
+    // The other components of the fitness are kept.
+    pub fn set_rate_args(&mut self, w_curve: f64, w_tags: f64, w_length: f64) {
+        let mut fitness = mem::take(&mut self.fitness);
+        for (component, weight) in Fitness::from_weights(w_curve, w_tags, w_length).0 {
+            if !fitness.reweight(&component.name(), weight) {
+                fitness.0.push((component, weight));
+            }
+        }
+        self.set_fitness(fitness);
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use crate::context::Context;
     use crate::deck::{Deck, DeckBTree};
-    use crate::population::{GA, POPULATION_SIZE};
+    use crate::population::{GA, POPULATION_SIZE, SEED_MUTATIONS};
 
     #[test]
     fn cull_and_params() {
@@ -180,6 +410,164 @@ mod tests {
         assert_eq!(ga.cull(), 0);
     }
 
+    #[test]
+    fn hall_of_fame() {
+        let ctx = Context::from_debug();
+        let mut ga: GA<DeckBTree> = GA::from_rand(ctx);
+        ga.set_rate_args(0.5, 0.0, 0.5);
+        ga.set_hall_of_fame_args(5, 10);
+        ga.update_params();
+        assert_eq!(ga.hall_of_fame.entries.len(), 5);
+        assert_eq!(ga.hall_of_fame.entries[0].1, ga.population[0].1);
+        for (i, (d1, _)) in ga.hall_of_fame.entries.iter().enumerate() {
+            for (d2, _) in &ga.hall_of_fame.entries[i + 1..] {
+                assert!(d1.distance(d2) >= 10);
+            }
+        }
+    }
+
+    #[test]
+    fn seed() {
+        let ctx = Context::from_debug();
+        let mut ga: GA<DeckBTree> = GA::from_rand(ctx);
+        let seeds = vec![DeckBTree::from_rand(&ga.ctx), DeckBTree::from_rand(&ga.ctx)];
+        ga.seed(&seeds, 0.25);
+        assert_eq!(ga.population[0].0.distance(&seeds[0]), 0);
+        assert_eq!(ga.population[1].0.distance(&seeds[1]), 0);
+        for i in 2..POPULATION_SIZE / 4 {
+            assert!(ga.population[i].0.distance(&seeds[i % 2]) <= SEED_MUTATIONS as i8);
+        }
+        // the rest is still random.
+        assert!(ga.population[POPULATION_SIZE / 4].0.distance(&seeds[0]) > SEED_MUTATIONS as i8);
+    }
+
+    #[test]
+    fn diversity() {
+        let ctx = Context::from_debug();
+        let mut ga: GA<DeckBTree> = GA::from_rand(ctx);
+        ga.set_rate_args(0.5, 0.0, 0.5);
+        ga.update_params();
+        assert_eq!(ga.unique_decks[0], POPULATION_SIZE);
+        assert!(ga.mean_distances[0] > 0.0);
+        assert!(ga.mean_tag_distances[0] > 0.0);
+        // a population of clones.
+        let deck = DeckBTree::from_rand(&ga.ctx);
+        ga.population.iter_mut().for_each(|(d, _)| *d = deck.clone());
+        ga.update_params();
+        assert_eq!(ga.unique_decks[1], 1);
+        assert_eq!(ga.mean_distances[1], 0.0);
+        assert_eq!(ga.mean_tag_distances[1], 0.0);
+    }
+
+    #[test]
+    fn elites() {
+        let ctx = Context::from_debug();
+        let mut ga: GA<DeckBTree> = GA::from_rand(ctx);
+        ga.set_rate_args(0.5, 0.0, 0.5);
+        ga.set_mutation_args(20, 3, 0.05);
+        ga.set_replacement_args(10, 0.1);
+        ga.update_params();
+        let elites: Vec<u64> = ga.population[..10].iter().map(|(d, _)| d.key()).collect();
+        assert!(ga.reproduce());
+        for i in 0..10 {
+            assert_eq!(ga.population[i].0.key(), elites[i]);
+        }
+    }
+
+    #[test]
+    fn immigrants() {
+        let ctx = Context::from_debug();
+        let mut ga: GA<DeckBTree> = GA::from_rand(ctx);
+        ga.set_rate_args(0.5, 0.0, 0.5);
+        ga.set_mutation_args(1, 1, 0.0);
+        ga.set_replacement_args(1, 0.25);
+        let clone = ga.population[0].0.clone();
+        for i in 0..POPULATION_SIZE {
+            ga.population[i].0 = clone.clone();
+        }
+        ga.update_params();
+        assert!(ga.reproduce());
+        // children of clones are at most a mutation away from them, random decks are far.
+        let far = ga.population.iter().filter(|(d, _)| d.distance(&clone) > 1).count();
+        assert_eq!(far, POPULATION_SIZE / 4);
+    }
+
+    #[test]
+    fn anchor() {
+        let ctx = Context::from_debug();
+        let mut ga: GA<DeckBTree> = GA::from_rand(ctx);
+        ga.set_rate_args(0.5, 0.0, 0.5);
+        ga.set_mutation_args(20, 3, 0.05);
+        let anchor = DeckBTree::from_rand(&ga.ctx);
+        ga.set_anchor(anchor.clone(), 2);
+        assert!(ga.tick());
+        assert!(ga.tick());
+        for (deck, _) in &ga.population {
+            assert!(deck.distance(&anchor) <= 2);
+        }
+    }
+
+    #[test]
+    fn cache() {
+        let ctx = Context::from_debug();
+        let mut ga: GA<DeckBTree> = GA::from_rand(ctx);
+        ga.set_rate_args(0.5, 0.0, 0.5);
+        for i in 1..POPULATION_SIZE / 2 {
+            ga.population[i].0 = ga.population[0].0.clone();
+        }
+        ga.update_params();
+        // random decks are very unlikely to be equal to each other.
+        assert!(ga.cache.hits >= (POPULATION_SIZE / 2 - 1) as u64);
+        assert!(ga.cache.misses <= (POPULATION_SIZE / 2 + 1) as u64);
+        let misses = ga.cache.misses;
+        ga.update_params();
+        assert_eq!(ga.cache.misses, misses);
+        // changing the fitness invalidates the scores.
+        ga.set_rate_args(0.0, 0.0, 1.0);
+        ga.update_params();
+        assert!(ga.cache.misses > misses);
+    }
+
+    #[test]
+    fn share() {
+        let ctx = Context::from_debug();
+        let mut ga: GA<DeckBTree> = GA::from_rand(ctx);
+        ga.set_niching_args(10, 1.0);
+        // 5 clones followed by 5 random decks, which are very unlikely to be close to each other.
+        for i in 0..10 {
+            if i < 5 {
+                ga.population[i].0 = ga.population[0].0.clone();
+            }
+            ga.population[i].1 = 1.0;
+        }
+        ga.share(9);
+        for i in 0..5 {
+            assert!(ga.population[i].1 > 0.9);
+        }
+        for i in 5..10 {
+            assert!(f64::abs(ga.population[i].1 - 0.2) < 0.0001);
+        }
+    }
+
+    #[test]
+    fn immigrate() {
+        let ctx = Context::from_debug();
+        let mut ga: GA<DeckBTree> = GA::from_rand(ctx);
+        ga.set_rate_args(0.5, 0.0, 0.5);
+        ga.update_params();
+        // a deck with a single copy of 40 cards has the worst length score.
+        let mut d = DeckBTree::new();
+        for i in 0..40 {
+            d.add(i);
+        }
+        let emigrants = ga.emigrants(3);
+        assert_eq!(emigrants.len(), 3);
+        assert_eq!(emigrants[0].distance(&ga.population[0].0), 0);
+        ga.immigrate(vec![emigrants[0].clone(), d]);
+        assert_eq!(ga.population[0].0.distance(&ga.population[1].0), 0);
+        assert!(ga.population[0].1 >= ga.population[POPULATION_SIZE - 1].1);
+    }
+
     #[test]
     fn select() {
         let ctx = Context::from_debug();
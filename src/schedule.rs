@@ -0,0 +1,115 @@
+// What a schedule can base its decisions on.
+pub struct Progress<'a> {
+    pub time: f64,
+    // The best score of each generation so far.
+    pub max_scores: &'a [f64],
+    // How different the decks of the current generation are from each other, between 0 (all
+    // clones) and 1 (no cards in common).
+    pub diversity: f64,
+}
+
+// Decides, generation by generation, the mutation temperature and the cull threshold, starting
+// from the arguments given to the GA.
+pub trait Schedule: Send {
+    fn params(&mut self,
+              mutation_args: (isize, isize, f64),
+              cull_args: (f64, f64, f64),
+              progress: &Progress) -> (isize, f64);
+}
+
+// The mutation temperature goes down and the cull threshold goes up linearly with time, until
+// they reach their respective limits.
+pub struct Linear;
+
+impl Schedule for Linear {
+    fn params(&mut self,
+              (temp, temp_min, temp_annealing): (isize, isize, f64),
+              (threshold, cap, annealing): (f64, f64, f64),
+              progress: &Progress) -> (isize, f64) {
+        let damp = (progress.time * temp_annealing).round() as isize;
+        (temp_min.max(temp - damp), (threshold + annealing * progress.time).min(cap))
+    }
+}
+
+// Anneals like Linear while the population improves, but when its diversity collapses or the best
+// score stops improving, the mutation temperature is raised back towards its starting value and
+// the cull threshold is lowered back towards its starting value, to escape the local optimum.
+pub struct Adaptive {
+    // The number of generations without improvement after which the population is stagnating.
+    pub patience: usize,
+    // The diversity under which the population is considered collapsed.
+    pub min_diversity: f64,
+    // The fraction of the distance from the starting values covered at each reheating.
+    pub reheat: f64,
+    state: Option<(f64, f64)>,
+}
+
+impl Adaptive {
+    pub fn new(patience: usize, min_diversity: f64, reheat: f64) -> Self {
+        Adaptive { patience, min_diversity, reheat, state: None }
+    }
+
+    fn stagnating(&self, max_scores: &[f64]) -> bool {
+        if max_scores.len() <= self.patience { return false; }
+        let (before, recent) = max_scores.split_at(max_scores.len() - self.patience);
+        let best = |scores: &[f64]| scores.iter().fold(f64::MIN, |a, b| a.max(*b));
+        best(recent) <= best(before)
+    }
+}
+
+impl Schedule for Adaptive {
+    fn params(&mut self,
+              (temp_max, temp_min, temp_annealing): (isize, isize, f64),
+              (threshold_min, cap, annealing): (f64, f64, f64),
+              progress: &Progress) -> (isize, f64) {
+        let (mut temp, mut threshold) = self.state.unwrap_or((temp_max as f64, threshold_min));
+        if progress.diversity < self.min_diversity || self.stagnating(progress.max_scores) {
+            temp += (temp_max as f64 - temp) * self.reheat;
+            threshold -= (threshold - threshold_min) * self.reheat;
+        } else {
+            temp = (temp - temp_annealing).max(temp_min as f64);
+            threshold = (threshold + annealing).min(cap);
+        }
+        self.state = Some((temp, threshold));
+        (temp.round() as isize, threshold)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schedule::{Adaptive, Linear, Progress, Schedule};
+
+    #[test]
+    fn linear() {
+        let progress = Progress { time: 10.0, max_scores: &[], diversity: 0.0 };
+        let (temp, threshold) = Linear.params((20, 3, 0.5), (0.3, 1.0, 0.01), &progress);
+        assert_eq!(temp, 15);
+        assert!(f64::abs(threshold - 0.4) < 1e-9);
+        assert_eq!(Linear.params((20, 3, 5.0), (0.3, 0.305, 0.01), &progress), (3, 0.305));
+    }
+
+    #[test]
+    fn adaptive() {
+        let mut s = Adaptive::new(2, 0.1, 0.5);
+        let improving = [0.1, 0.2, 0.3, 0.4];
+        let mut params = (0, 0.0);
+        for i in 0..10 {
+            let progress = Progress { time: i as f64, max_scores: &improving, diversity: 0.5 };
+            params = s.params((20, 3, 1.0), (0.3, 1.0, 0.01), &progress);
+        }
+        // while improving, it anneals.
+        assert_eq!(params.0, 10);
+        assert!(f64::abs(params.1 - 0.4) < 1e-9);
+        // when the best score stagnates, it reheats.
+        let stagnating = [0.1, 0.4, 0.4, 0.4];
+        let progress = Progress { time: 10.0, max_scores: &stagnating, diversity: 0.5 };
+        let (temp, threshold) = s.params((20, 3, 1.0), (0.3, 1.0, 0.01), &progress);
+        assert_eq!(temp, 15);
+        assert!(f64::abs(threshold - 0.35) < 1e-9);
+        // as it does when the diversity collapses.
+        let progress = Progress { time: 11.0, max_scores: &improving, diversity: 0.01 };
+        let (temp, threshold) = s.params((20, 3, 1.0), (0.3, 1.0, 0.01), &progress);
+        assert!(temp > 15);
+        assert!(threshold < 0.35);
+    }
+}
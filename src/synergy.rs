@@ -0,0 +1,148 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use fnv::FnvHashMap;
+
+use crate::context::Context;
+use crate::deck::DeckBTree;
+use crate::decklist::read_decks;
+use crate::fitness::FitnessComponent;
+
+// Reads every deck in the files of the directory, or in the file if given one. Decks for other
+// crafts are skipped (see read_decks).
+pub fn read_corpus(ctx: &Context, path: &str) -> Result<Vec<DeckBTree>, io::Error> {
+    if !Path::new(path).is_dir() {
+        return Ok(read_decks(ctx, path)?.0);
+    }
+    let mut files: Vec<_> = fs::read_dir(path)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.is_file())
+        .collect();
+    files.sort();
+    let mut ret = vec![];
+    for file in files {
+        ret.extend(read_decks(ctx, &file.to_string_lossy())?.0);
+    }
+    Ok(ret)
+}
+
+// How often pairs of cards appear together in a corpus of human decks.
+// Each pair is weighted by its normalized pointwise mutual information: the log of how much more
+// often the cards are played together than if they were picked independently, scaled from -1 (never
+// together) through 0 (independent) to 1 (always together). Only whether a deck plays a card counts,
+// not how many copies.
+pub struct Synergy {
+    // the number of decks of the corpus.
+    pub decks: usize,
+    // the number of decks that play each card.
+    pub cards: FnvHashMap<usize, usize>,
+    // the number of decks that play each pair of cards, smallest index first.
+    pub pairs: FnvHashMap<(usize, usize), usize>,
+}
+
+impl Synergy {
+    pub fn from_decks(decks: &[DeckBTree]) -> Synergy {
+        let mut cards = FnvHashMap::default();
+        let mut pairs = FnvHashMap::default();
+        for deck in decks {
+            let idxs: Vec<usize> = deck.0.keys().copied().collect();
+            for (i, a) in idxs.iter().enumerate() {
+                *cards.entry(*a).or_insert(0) += 1;
+                for b in &idxs[i + 1..] {
+                    *pairs.entry((*a, *b)).or_insert(0) += 1;
+                }
+            }
+        }
+        Synergy { decks: decks.len(), cards, pairs }
+    }
+
+    // The normalized pmi of the two cards, or None if either never appears in the corpus.
+    pub fn npmi(&self, a: usize, b: usize) -> Option<f64> {
+        let key = if a < b { (a, b) } else { (b, a) };
+        let n = self.decks as f64;
+        let pa = *self.cards.get(&a)? as f64 / n;
+        let pb = *self.cards.get(&b)? as f64 / n;
+        let pab = *self.pairs.get(&key).unwrap_or(&0) as f64 / n;
+        if pab == 0.0 { return Some(-1.0); }
+        if pab == 1.0 { return Some(1.0); }
+        Some((pab / (pa * pb)).ln() / -pab.ln())
+    }
+
+    // The pairs of cards that appear together in at least min_decks decks, strongest first.
+    pub fn strongest(&self, min_decks: usize) -> Vec<((usize, usize), f64)> {
+        let mut ret: Vec<((usize, usize), f64)> = self.pairs.iter()
+            .filter(|(_, n)| **n >= min_decks)
+            .map(|((a, b), _)| ((*a, *b), self.npmi(*a, *b).unwrap()))
+            .collect();
+        ret.sort_by(|(x, a), (y, b)| b.partial_cmp(a).unwrap().then(x.cmp(y)));
+        ret
+    }
+
+    pub fn as_string(&self, ctx: &Context, n: usize, min_decks: usize) -> String {
+        let mut ret = format!("{} decks, {} cards, {} pairs. Strongest pairs in at least {} decks:\n",
+                              self.decks, self.cards.len(), self.pairs.len(), min_decks);
+        for ((a, b), npmi) in self.strongest(min_decks).into_iter().take(n) {
+            ret.push_str(&format!("\t{:.3}\t{} + {} ({} decks)\n", npmi,
+                                  ctx.idx_to_card(a).name_, ctx.idx_to_card(b).name_,
+                                  self.pairs[&(a, b)]));
+        }
+        ret
+    }
+}
+
+// The mean npmi of the pairs of copies of the deck, scaled from 0 to 1. Pairs with a card unknown
+// to the corpus count as independent.
+impl FitnessComponent for Synergy {
+    fn name(&self) -> String {
+        String::from("synergy")
+    }
+
+    fn score(&self, _ctx: &Context, deck: &DeckBTree) -> f64 {
+        let cards: Vec<(&usize, &i8)> = deck.0.iter().collect();
+        let (mut sum, mut weight) = (0.0, 0.0);
+        for (i, (a, qa)) in cards.iter().enumerate() {
+            for (b, qb) in &cards[i + 1..] {
+                let w = (**qa * **qb) as f64;
+                sum += w * self.npmi(**a, **b).unwrap_or(0.0);
+                weight += w;
+            }
+        }
+        if weight == 0.0 { return 0.5; }
+        (sum / weight + 1.0) / 2.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::Context;
+    use crate::deck::{Deck, DeckBTree};
+    use crate::fitness::FitnessComponent;
+    use crate::synergy::Synergy;
+
+    fn deck(cards: &[usize]) -> DeckBTree {
+        let mut ret = DeckBTree::new();
+        cards.iter().for_each(|idx| ret.add(*idx));
+        ret
+    }
+
+    #[test]
+    fn graph() {
+        let ctx = Context::from_debug();
+        // 0 and 1 always go together, 2 and 3 never do.
+        let corpus = vec![deck(&[0, 1, 2]), deck(&[0, 1, 3]), deck(&[4, 2]), deck(&[4, 3])];
+        let s = Synergy::from_decks(&corpus);
+        assert_eq!(s.decks, 4);
+        assert_eq!(s.pairs[&(0, 1)], 2);
+        assert!(f64::abs(s.npmi(1, 0).unwrap() - 1.0) < 1e-9);
+        assert_eq!(s.npmi(2, 3), Some(-1.0));
+        assert_eq!(s.npmi(0, 5), None);
+        // 0 and 2 appear together exactly as often as if they were independent.
+        assert!(f64::abs(s.npmi(0, 2).unwrap()) < 1e-9);
+        assert_eq!(s.strongest(2)[0].0, (0, 1));
+        let good = s.score(&ctx, &deck(&[0, 1]));
+        let bad = s.score(&ctx, &deck(&[2, 3]));
+        assert!(f64::abs(good - 1.0) < 1e-9 && bad == 0.0);
+        assert_eq!(s.score(&ctx, &deck(&[5, 6])), 0.5);
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::archetype::{label, tag_densities};
+use crate::context::Context;
+use crate::deck::{Deck, DeckBTree, PP_CURVE_SIZE};
+use crate::fitness::{CARD_TYPES, TagDensities, TypeRanges, Types};
+
+// The archetype of the targets learned from every deck of the corpus.
+pub const ALL_ARCHETYPES: &str = "All";
+// Tags carried by fewer of the copies, on average, aren't targeted.
+pub const MIN_TAG_DENSITY: f64 = 0.05;
+// The least room a type range leaves around the mean share of the type.
+pub const TYPE_TOLERANCE: f64 = 0.05;
+
+// What the decks of an archetype look like on average: the fitness targets learned from them.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Targets {
+    pub craft: String,
+    pub archetype: String,
+    pub decks: usize,
+    // The mean number of copies in each bucket of the pp curve.
+    pub curve: [f64; PP_CURVE_SIZE],
+    // The mean share of each card type, plus or minus its standard deviation, in the order of
+    // CARD_TYPES.
+    pub types: TypeRanges,
+    // The mean fraction of the copies carrying each tag.
+    pub tags: BTreeMap<String, f64>,
+}
+
+impl Targets {
+    pub fn from_decks(ctx: &Context, archetype: &str, decks: &[DeckBTree]) -> Self {
+        let n = decks.len() as f64;
+        let mut curve = [0.0; PP_CURVE_SIZE];
+        let mut shares = [(0.0, 0.0); 3];
+        let mut tags = BTreeMap::new();
+        for deck in decks {
+            for (c, qty) in curve.iter_mut().zip(deck.pp_curve(ctx)) {
+                *c += qty as f64 / n;
+            }
+            for ((sum, sum_sq), count) in shares.iter_mut().zip(Types::counts(ctx, deck)) {
+                let share = count as f64 / deck.len() as f64;
+                *sum += share / n;
+                *sum_sq += share * share / n;
+            }
+            for (tag, density) in tag_densities(ctx, deck) {
+                *tags.entry(tag.to_string()).or_insert(0.0) += density / n;
+            }
+        }
+        tags.retain(|_, density| *density >= MIN_TAG_DENSITY);
+        let types = shares.map(|(mean, mean_sq): (f64, f64)| {
+            let margin = (mean_sq - mean * mean).max(0.0).sqrt().max(TYPE_TOLERANCE);
+            ((mean - margin).max(0.0), (mean + margin).min(1.0))
+        });
+        Targets {
+            craft: ctx.craft_name().to_string(),
+            archetype: archetype.to_string(),
+            decks: decks.len(),
+            curve,
+            types,
+            tags,
+        }
+    }
+
+    // The ideal curve for the Curve component.
+    pub fn ideal_curve(&self) -> [i32; PP_CURVE_SIZE] {
+        self.curve.map(|qty| qty.round() as i32)
+    }
+
+    pub fn types(&self) -> Types {
+        Types { ranges: self.types }
+    }
+
+    pub fn tag_densities(&self) -> TagDensities {
+        TagDensities { targets: self.tags.iter().map(|(t, d)| (t.clone(), *d)).collect() }
+    }
+
+    pub fn as_string(&self) -> String {
+        let curve: Vec<String> = self.curve.iter().map(|qty| format!("{:.1}", qty)).collect();
+        let mut ret = format!("{} ({} decks)\n\tcurve: {}\n\ttypes:", self.archetype, self.decks,
+                              curve.join(", "));
+        for (name, (lo, hi)) in CARD_TYPES.iter().zip(self.types) {
+            ret.push_str(&format!(" {} {:.0}-{:.0}%", name, 100.0 * lo, 100.0 * hi));
+        }
+        ret.push_str("\n\ttags:");
+        for (tag, density) in &self.tags {
+            ret.push_str(&format!(" {} {:.1}%", tag, 100.0 * density));
+        }
+        ret
+    }
+}
+
+// The targets of every deck of the corpus, then those of each archetype (see archetype.rs).
+// Averages over no decks mean nothing, so an empty corpus is an error.
+pub fn learn(ctx: &Context, decks: &[DeckBTree]) -> Result<Vec<Targets>, String> {
+    if decks.is_empty() {
+        return Err(format!("no {} decks to learn targets from", ctx.craft_name()));
+    }
+    let mut by_archetype: BTreeMap<String, Vec<DeckBTree>> = BTreeMap::new();
+    for deck in decks {
+        by_archetype.entry(label(ctx, deck)).or_default().push(deck.clone());
+    }
+    let mut ret = vec![Targets::from_decks(ctx, ALL_ARCHETYPES, decks)];
+    for (archetype, decks) in by_archetype {
+        ret.push(Targets::from_decks(ctx, &archetype, &decks));
+    }
+    Ok(ret)
+}
+
+pub fn load(path: &str) -> Result<Vec<Targets>, io::Error> {
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+// Saves the targets into the file, replacing the ones it holds for the same crafts and keeping
+// those of the other crafts.
+pub fn save(targets: &[Targets], path: &str) -> Result<(), io::Error> {
+    let mut all = if Path::new(path).exists() { load(path)? } else { vec![] };
+    all.retain(|old| targets.iter().all(|t| t.craft != old.craft));
+    all.extend(targets.iter().cloned());
+    fs::write(path, serde_json::to_string_pretty(&all)?)
+}
+
+// The targets for the chosen craft and the given archetype. Without one, the first archetype with
+// a chosen tag in its name, or else those of every deck.
+pub fn choose<'a>(ctx: &Context, targets: &'a [Targets], archetype: Option<&str>)
+                  -> Result<&'a Targets, String> {
+    let craft: Vec<&Targets> = targets.iter().filter(|t| t.craft == ctx.craft_name()).collect();
+    let found = match archetype {
+        Some(archetype) => craft.iter().find(|t| t.archetype.eq_ignore_ascii_case(archetype)),
+        None => craft.iter()
+            .find(|t| ctx.tags.iter().any(|tag| t.archetype.split(' ').any(|w| w == tag)))
+            .or_else(|| craft.iter().find(|t| t.archetype == ALL_ARCHETYPES)),
+    };
+    found.copied().ok_or(format!("no targets for {} {}", archetype.unwrap_or(ALL_ARCHETYPES),
+                                 ctx.craft_name()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use crate::context::Context;
+    use crate::deck::{Deck, DeckBTree};
+    use crate::fitness::FitnessComponent;
+    use crate::targets::{ALL_ARCHETYPES, choose, learn, load, save, Targets};
+
+    #[test]
+    fn targets() {
+        let ctx = Context::from_debug();
+        let d = DeckBTree::from_rand(&ctx);
+        // the targets of copies of a single deck are the deck itself.
+        let t = Targets::from_decks(&ctx, ALL_ARCHETYPES, &[d.clone(), d.clone()]);
+        assert_eq!(t.ideal_curve(), d.pp_curve(&ctx));
+        assert_eq!(t.types().score(&ctx, &d), 1.0);
+        assert!(f64::abs(t.tag_densities().score(&ctx, &d) - 1.0) < 1e-9);
+        let all = learn(&ctx, &[d.clone(), DeckBTree::from_rand(&ctx)]).unwrap();
+        assert_eq!(all[0].archetype, ALL_ARCHETYPES);
+        assert_eq!(all[1..].iter().map(|t| t.decks).sum::<usize>(), 2);
+        assert_eq!(choose(&ctx, &all, Some(ALL_ARCHETYPES)), Ok(&all[0]));
+        assert!(choose(&ctx, &all, Some("Nothing")).is_err());
+        let path = env::temp_dir().join("shadowgen_targets_test.json");
+        let path = path.to_str().unwrap();
+        let _ = fs::remove_file(path);
+        save(&all, path).unwrap();
+        assert_eq!(load(path).unwrap().len(), all.len());
+        // saving again replaces the targets of the craft, and keeps those of the others.
+        let mut other = all[0].clone();
+        other.craft = String::from("Forestcraft");
+        save(&[other.clone()], path).unwrap();
+        save(&all[..1], path).unwrap();
+        let saved = load(path).unwrap();
+        fs::remove_file(path).unwrap();
+        // floats may not survive the round trip to json exactly.
+        let keys: Vec<(&str, &str)> = saved.iter()
+            .map(|t| (t.craft.as_str(), t.archetype.as_str()))
+            .collect();
+        assert_eq!(keys, vec![("Forestcraft", ALL_ARCHETYPES), ("Swordcraft", ALL_ARCHETYPES)]);
+        // an empty corpus has no targets.
+        assert!(learn(&ctx, &[]).is_err());
+    }
+}
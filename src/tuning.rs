@@ -0,0 +1,304 @@
+use std::fs;
+use std::io;
+
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+use crate::context::Context;
+use crate::deck::{Deck, DeckBTree};
+use crate::fitness::Fitness;
+use crate::locus::LocusOrdering;
+use crate::optimizer::Optimizer;
+use crate::population::GA;
+
+// The GA settings that are tuned, in the form taken by the respective setters.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Preset {
+    pub rate_args: (f64, f64, f64),
+    pub mutation_args: (isize, isize, f64),
+    pub cull_args: (f64, f64, f64),
+}
+
+impl Preset {
+    // The other components of the GA's fitness are kept, see GA::set_rate_args.
+    pub fn apply(&self, g: &mut GA<DeckBTree>) {
+        let (w_curve, w_tags, w_length) = self.rate_args;
+        g.set_rate_args(w_curve, w_tags, w_length);
+        let (temp, starting_temp, temp_annealing) = self.mutation_args;
+        g.set_mutation_args(temp, starting_temp, temp_annealing);
+        let (threshold, cap, annealing) = self.cull_args;
+        g.set_cull_args(threshold, cap, annealing);
+    }
+
+    pub fn load(path: &str) -> Result<Self, io::Error> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), io::Error> {
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+}
+
+// The candidate values of each group of settings.
+pub struct Space {
+    pub rate_args: Vec<(f64, f64, f64)>,
+    pub mutation_args: Vec<(isize, isize, f64)>,
+    pub cull_args: Vec<(f64, f64, f64)>,
+}
+
+impl Default for Space {
+    fn default() -> Self {
+        Space {
+            rate_args: vec![(0.4, 0.4, 0.2), (0.6, 0.2, 0.2), (0.2, 0.6, 0.2), (0.4, 0.2, 0.4)],
+            mutation_args: vec![(10, 3, 0.05), (20, 3, 0.05), (20, 3, 0.2), (30, 5, 0.1)],
+            cull_args: vec![(0.2, 0.8, 0.005), (0.3, 1.0, 0.005), (0.3, 1.0, 0.02)],
+        }
+    }
+}
+
+impl Space {
+    // Every combination of the candidate values.
+    pub fn grid(&self) -> Vec<Preset> {
+        let mut ret = vec![];
+        for rate_args in &self.rate_args {
+            for mutation_args in &self.mutation_args {
+                for cull_args in &self.cull_args {
+                    ret.push(Preset {
+                        rate_args: *rate_args,
+                        mutation_args: *mutation_args,
+                        cull_args: *cull_args,
+                    });
+                }
+            }
+        }
+        ret
+    }
+
+    // n random combinations of the candidate values, without repetitions.
+    pub fn sample(&self, n: usize) -> Vec<Preset> {
+        let mut ret = self.grid();
+        ret.shuffle(&mut rand::thread_rng());
+        ret.truncate(n);
+        ret
+    }
+}
+
+// The number of evenly spaced generations at which a trial records the best fitness reached so
+// far, the last being the end of the runs.
+pub const CHECKPOINTS: usize = 4;
+
+// The generations of the checkpoints of runs of the given length. Checkpoints that come before
+// the first generation get its best fitness.
+pub fn checkpoints(generations: f64) -> Vec<f64> {
+    (1..=CHECKPOINTS).map(|i| generations * i as f64 / CHECKPOINTS as f64).collect()
+}
+
+// How a preset fared over several runs.
+#[derive(Clone, Debug)]
+pub struct Trial {
+    pub preset: Preset,
+    // The mean over the runs of the best reference fitness reached.
+    pub best: f64,
+    // The mean over the runs of the best reference fitness reached by each checkpoint.
+    pub progress: Vec<f64>,
+    // The mean over the runs of the generations needed to reach the target. Runs that never reach
+    // it count as taking all of their generations.
+    pub time_to_target: f64,
+    // The number of runs that reached the target.
+    pub hits: usize,
+}
+
+// Runs the GA made by base, set up with the preset, repeats times for the given number of
+// generations each.
+// Presets can change the weights of the fitness, so the decks are compared on a reference fitness
+// that is the same for every preset: a preset is better if the GA it drives finds better decks by
+// that measure, whatever the fitness it optimizes along the way. Runs go on after reaching the
+// target, so that the best score is the one reached by the end of the run.
+// NOTE: the GA draws from thread_rng, which can't be seeded, so repeated runs stand in for seeds.
+pub fn evaluate<F>(base: F, preset: &Preset, reference: &Fitness, repeats: usize,
+                   generations: f64, target: f64) -> Trial
+    where F: Fn() -> GA<DeckBTree> {
+    let mut trial = Trial {
+        preset: preset.clone(),
+        best: 0.0,
+        progress: vec![0.0; CHECKPOINTS],
+        time_to_target: 0.0,
+        hits: 0,
+    };
+    let checkpoints = checkpoints(generations);
+    for _ in 0..repeats {
+        let mut g = base();
+        preset.apply(&mut g);
+        // the target of the GA follows the weights of the preset.
+        g.set_stop_condition(g.fitness.total_weight());
+        let mut best = f64::MIN;
+        let mut progress = vec![f64::MIN; CHECKPOINTS];
+        let mut hit = None;
+        while g.time < generations {
+            let going = g.tick();
+            best = best.max(g.best().0.rate(&g.ctx, reference));
+            for (p, checkpoint) in progress.iter_mut().zip(&checkpoints) {
+                if g.time <= *checkpoint || *p == f64::MIN { *p = best; }
+            }
+            if hit.is_none() && best >= target {
+                hit = Some(g.time);
+            }
+            if !going { break; }
+        }
+        trial.best += best;
+        for (mean, p) in trial.progress.iter_mut().zip(progress) {
+            *mean += p / repeats as f64;
+        }
+        trial.time_to_target += hit.unwrap_or(generations);
+        trial.hits += hit.is_some() as usize;
+    }
+    trial.best /= repeats as f64;
+    trial.time_to_target /= repeats as f64;
+    trial
+}
+
+// The context with its cards in the given order, and the fitness that make_fitness builds for it.
+// Components like Synergy, or Consistency on a card, store card indexes, which the new order
+// changes, so the fitness can't be built before reordering.
+pub fn reordered<F>(ctx: &Context, ordering: &dyn LocusOrdering, make_fitness: F)
+    -> (Context, Fitness) where F: Fn(&Context) -> Fitness {
+    let mut ctx = ctx.clone();
+    ctx.reorder(ordering);
+    let fitness = make_fitness(&ctx);
+    (ctx, fitness)
+}
+
+// Best first: by reference fitness, then by time to target.
+pub fn rank(trials: &mut [Trial]) {
+    trials.sort_by(|a, b| {
+        b.best.partial_cmp(&a.best).unwrap()
+            .then(a.time_to_target.partial_cmp(&b.time_to_target).unwrap())
+    });
+}
+
+pub fn trials_as_string(trials: &[Trial], repeats: usize) -> String {
+    let mut ret = String::new();
+    for (i, t) in trials.iter().enumerate() {
+        ret.push_str(&format!("#{}\tbest: {:.4}\ttime to target: {:.1} ({}/{})\t{:?}\n",
+                              i + 1, t.best, t.time_to_target, t.hits, repeats, t.preset));
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use crate::context::Context;
+    use crate::deck::{Deck, DeckBTree};
+    use crate::fitness::{Fitness, Roles};
+    use crate::locus::Shuffled;
+    use crate::population::GA;
+    use crate::synergy::Synergy;
+    use crate::tuning::{CHECKPOINTS, evaluate, Preset, rank, reordered, Space, Trial};
+
+    fn preset() -> Preset {
+        Preset { rate_args: (0.5, 0.0, 0.5), mutation_args: (10, 3, 0.05), cull_args: (0.0, 0.0, 0.0) }
+    }
+
+    #[test]
+    fn space() {
+        let s = Space::default();
+        let grid = s.grid();
+        assert_eq!(grid.len(), s.rate_args.len() * s.mutation_args.len() * s.cull_args.len());
+        let sample = s.sample(5);
+        assert_eq!(sample.len(), 5);
+        for (i, p) in sample.iter().enumerate() {
+            assert!(grid.contains(p));
+            assert!(!sample[i + 1..].contains(p));
+        }
+        assert_eq!(s.sample(1_000).len(), grid.len());
+    }
+
+    #[test]
+    fn save_and_load() {
+        let path = env::temp_dir().join("shadowgen_preset_test.json");
+        let path = path.to_str().unwrap();
+        preset().save(path).unwrap();
+        assert_eq!(Preset::load(path).unwrap(), preset());
+    }
+
+    #[test]
+    fn evaluation() {
+        let ctx = Context::from_debug();
+        let base = || GA::<DeckBTree>::from_rand(ctx.clone());
+        let reference = Fitness::from_weights(0.5, 0.0, 0.5);
+        // an unreachable target: every run goes on for all of its generations.
+        let t = evaluate(base, &preset(), &reference, 2, 2.0, 2.0);
+        assert_eq!(t.hits, 0);
+        assert_eq!(t.time_to_target, 2.0);
+        assert!(t.best > 0.0);
+        // the best fitness never drops, and it is reached by the last checkpoint.
+        assert!(t.progress.windows(2).all(|w| w[0] <= w[1]));
+        assert!(f64::abs(t.progress[CHECKPOINTS - 1] - t.best) < 1e-9);
+        // a target reached on the first generation: the run still goes on.
+        let t = evaluate(base, &preset(), &reference, 1, 3.0, 0.0);
+        assert_eq!(t.hits, 1);
+        assert_eq!(t.time_to_target, 1.0);
+    }
+
+    #[test]
+    fn reordering() {
+        let ctx = Context::from_debug();
+        let corpus = DeckBTree::from_rand(&ctx);
+        let ids: Vec<(i32, i8)> = corpus.0.iter()
+            .map(|(idx, qty)| (ctx.idx_to_card(*idx).id_, *qty))
+            .collect();
+        // the corpus as read under the given order of the cards.
+        let read = |ctx: &Context| {
+            let mut ret = DeckBTree::new();
+            for (id, qty) in &ids {
+                for _ in 0..*qty { ret.add(ctx.id_to_idx(*id).unwrap()); }
+            }
+            ret
+        };
+        let make_fitness = |ctx: &Context| {
+            let mut ret = Fitness::default();
+            ret.add(Box::new(Synergy::from_decks(&[read(ctx)])), 1.0);
+            ret
+        };
+        let (shuffled, fitness) = reordered(&ctx, &Shuffled, make_fitness);
+        // the corpus deck plays only pairs that always go together, whatever the order.
+        assert!(f64::abs(make_fitness(&ctx).rate(&ctx, &corpus) - 1.0) < 1e-9);
+        assert!(f64::abs(fitness.rate(&shuffled, &read(&shuffled)) - 1.0) < 1e-9);
+        let base = || {
+            let mut g = GA::<DeckBTree>::from_rand(shuffled.clone());
+            g.set_fitness(fitness.clone());
+            g
+        };
+        let t = evaluate(base, &preset(), &fitness, 1, 2.0, 2.0);
+        assert!((0.0..=1.0).contains(&t.best));
+    }
+
+    #[test]
+    fn apply() {
+        let ctx = Context::from_debug();
+        let mut g = GA::<DeckBTree>::from_rand(ctx.clone());
+        g.set_rate_args(0.4, 0.4, 0.2);
+        g.fitness.add(Box::new(Roles::default()), 0.3);
+        preset().apply(&mut g);
+        // the other components are kept.
+        assert_eq!(g.fitness.names(), vec!["curve", "tags", "length", "roles"]);
+        let weights: Vec<f64> = g.fitness.0.iter().map(|(_, w)| *w).collect();
+        assert_eq!(weights, vec![0.5, 0.0, 0.5, 0.3]);
+        // the stop condition is left to the caller.
+        assert_eq!(g.target_fitness, 1.0);
+        assert_eq!(g.mutation_args, preset().mutation_args);
+    }
+
+    #[test]
+    fn ranking() {
+        let trial = |best, time_to_target| {
+            Trial { preset: preset(), best, progress: vec![], time_to_target, hits: 0 }
+        };
+        let mut trials = vec![trial(0.5, 3.0), trial(0.7, 9.0), trial(0.7, 4.0)];
+        rank(&mut trials);
+        let order: Vec<(f64, f64)> = trials.iter().map(|t| (t.best, t.time_to_target)).collect();
+        assert_eq!(order, vec![(0.7, 4.0), (0.7, 9.0), (0.5, 3.0)]);
+    }
+}
@@ -0,0 +1,72 @@
+use crate::context::Context;
+use crate::deck::{Deck, DeckBTree};
+use crate::fitness::Fitness;
+
+// Cards, with a number of copies.
+pub type Copies = Vec<(usize, i8)>;
+
+// The cards to cut from a deck and the cards to add to it to turn it into the other.
+pub fn swaps(from: &DeckBTree, to: &DeckBTree) -> (Copies, Copies) {
+    fn excess(a: &DeckBTree, b: &DeckBTree) -> Copies {
+        a.0.iter()
+            .map(|(idx, qty)| (*idx, qty - b.0.get(idx).unwrap_or(&0)))
+            .filter(|(_, qty)| *qty > 0)
+            .collect()
+    }
+    (excess(from, to), excess(to, from))
+}
+
+// The swaps in the "-2 X, +2 Y" form.
+pub fn swaps_as_string(ctx: &Context, from: &DeckBTree, to: &DeckBTree) -> String {
+    let (cuts, adds) = swaps(from, to);
+    let cuts = cuts.iter().map(|(idx, qty)| format!("-{} {}", qty, ctx.idx_to_card(*idx).name_));
+    let adds = adds.iter().map(|(idx, qty)| format!("+{} {}", qty, ctx.idx_to_card(*idx).name_));
+    let ret: Vec<String> = cuts.chain(adds).collect();
+    if ret.is_empty() { String::from("no changes") } else { ret.join(", ") }
+}
+
+// The suggested swaps, and how they change the overall score and each of its components.
+pub fn report(ctx: &Context, fitness: &Fitness, from: &DeckBTree, to: &DeckBTree) -> String {
+    let (before, after) = (from.rate(ctx, fitness), to.rate(ctx, fitness));
+    let mut ret = format!("{}\n\tfitness: {:.4} -> {:.4} ({:+.4})\n",
+                          swaps_as_string(ctx, from, to), before, after, after - before);
+    let components = fitness.names().into_iter()
+        .zip(from.objectives(ctx, fitness))
+        .zip(to.objectives(ctx, fitness));
+    for ((name, before), after) in components {
+        ret.push_str(&format!("\t{}: {:.4} -> {:.4} ({:+.4})\n", name, before, after, after - before));
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::Context;
+    use crate::deck::{Deck, DeckBTree};
+    use crate::fitness::Fitness;
+    use crate::upgrade::{report, swaps, swaps_as_string};
+
+    #[test]
+    fn swap_list() {
+        let ctx = Context::from_debug();
+        let mut d1 = DeckBTree::new();
+        let mut d2 = DeckBTree::new();
+        for i in 0..20 {
+            d1.add(i);
+            d1.add(i);
+            d2.add(i);
+            d2.add(i);
+        }
+        d2.cut(0);
+        d2.cut(0);
+        d2.add(20);
+        d2.add(20);
+        assert_eq!(swaps(&d1, &d2), (vec![(0, 2)], vec![(20, 2)]));
+        assert_eq!(swaps_as_string(&ctx, &d1, &d2),
+                   format!("-2 {}, +2 {}", ctx.idx_to_card(0).name_, ctx.idx_to_card(20).name_));
+        assert_eq!(swaps_as_string(&ctx, &d1, &d1), "no changes");
+        let f = Fitness::from_weights(0.4, 0.4, 0.2);
+        // a line for the swaps, one for the overall fitness, one for each component.
+        assert_eq!(report(&ctx, &f, &d1, &d2).lines().count(), 5);
+    }
+}